@@ -0,0 +1,90 @@
+/*
+ * Exercises the C interface, run with `make -C ffi test` after
+ * `cargo build`.
+ */
+
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "vohtori.h"
+
+static void test_numbers(VohtoriRuntime *runtime) {
+    VohtoriValue *value = vohtori_eval(runtime, "xs = [1 2 3]\nxs * 2");
+    assert(value != NULL);
+    assert(vohtori_last_error(runtime) == NULL);
+
+    assert(vohtori_value_type(value) == VOHTORI_TYPE_ARRAY);
+    assert(vohtori_value_len(value) == 3);
+
+    for (size_t i = 0; i < 3; i++) {
+        const VohtoriValue *element = vohtori_value_element(value, i);
+        assert(vohtori_value_type(element) == VOHTORI_TYPE_NUMBER);
+        assert(vohtori_value_number(element) == (int64_t)(i + 1) * 2);
+    }
+    assert(vohtori_value_element(value, 3) == NULL);
+
+    vohtori_value_free(value);
+
+    /* Variables persist between evaluations. */
+    value = vohtori_eval(runtime, "+ xs");
+    assert(vohtori_value_number(value) == 6);
+    vohtori_value_free(value);
+}
+
+static void test_maps_and_strings(VohtoriRuntime *runtime) {
+    VohtoriValue *value = vohtori_eval(runtime, "{\"b\": [4] \"a\": 5}");
+    assert(value != NULL);
+    assert(vohtori_value_type(value) == VOHTORI_TYPE_MAP);
+    assert(vohtori_value_len(value) == 2);
+
+    size_t len = 0;
+    const char *key = vohtori_value_string(vohtori_value_key(value, 0), &len);
+    assert(len == 1 && strncmp(key, "a", len) == 0);
+    assert(vohtori_value_number(vohtori_value_entry(value, 0)) == 5);
+    assert(vohtori_value_type(vohtori_value_entry(value, 1)) == VOHTORI_TYPE_ARRAY);
+
+    char *display = vohtori_value_display(value);
+    assert(strcmp(display, "{\"a\": 5 \"b\": [4]}") == 0);
+    vohtori_string_free(display);
+
+    vohtori_value_free(value);
+}
+
+static void test_ranges(VohtoriRuntime *runtime) {
+    /* Walking a value reads elements one at a time, so a range this long
+     * is never stored. */
+    VohtoriValue *value = vohtori_eval(runtime, "1..4000000000000000000");
+    assert(value != NULL);
+    assert(vohtori_value_len(value) == 4000000000000000000);
+
+    const VohtoriValue *last = vohtori_value_element(value, 3999999999999999999);
+    assert(vohtori_value_number(last) == 4000000000000000000);
+    /* The same element is handed out again. */
+    assert(vohtori_value_element(value, 3999999999999999999) == last);
+
+    vohtori_value_free(value);
+}
+
+static void test_errors(VohtoriRuntime *runtime) {
+    assert(vohtori_eval(runtime, "undefined") == NULL);
+    assert(strcmp(vohtori_last_error(runtime), "Undefined variable undefined") == 0);
+
+    VohtoriValue *value = vohtori_eval(runtime, "1");
+    assert(vohtori_last_error(runtime) == NULL);
+    vohtori_value_free(value);
+}
+
+int main(void) {
+    VohtoriRuntime *runtime = vohtori_runtime_new();
+
+    test_numbers(runtime);
+    test_maps_and_strings(runtime);
+    test_ranges(runtime);
+    test_errors(runtime);
+
+    vohtori_runtime_free(runtime);
+    puts("ok");
+
+    return 0;
+}
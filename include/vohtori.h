@@ -0,0 +1,136 @@
+/*
+ * C interface to the vohtori interpreter, generated from src/ffi.rs.
+ *
+ * Evaluation recurses on the calling thread's stack, deeply recursive
+ * scripts need a thread with VOHTORI_STACK_SIZE bytes of stack.
+ */
+
+#ifndef VOHTORI_H
+#define VOHTORI_H
+
+/* Generated by cbindgen, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Evaluation recurses on the native stack. A thread with less room than
+// this reports `RuntimeError::RecursionLimit` before reaching the default
+// `Limits::max_depth` of nested calls, rather than overflowing.
+#define VOHTORI_STACK_SIZE ((256 * 1024) * 1024)
+
+typedef enum {
+  VOHTORI_TYPE_ARRAY,
+  VOHTORI_TYPE_MAP,
+  VOHTORI_TYPE_NUMBER,
+  VOHTORI_TYPE_STRING,
+  VOHTORI_TYPE_FUNCTION,
+} VohtoriType;
+
+// A runtime together with the message of the last failed evaluation.
+typedef struct VohtoriRuntime VohtoriRuntime;
+
+// A value handed out to C. Elements and map entries are only taken out of
+// it the first time they are asked for, and are then kept so that they can
+// be borrowed until the root is freed. Walking a value never stores more
+// than what was read, so a large range stays a range.
+typedef struct VohtoriValue VohtoriValue;
+
+VohtoriRuntime *vohtori_runtime_new(void);
+
+// # Safety
+//
+// `runtime` must come from `vohtori_runtime_new` and not be used afterwards.
+// Null is ignored.
+void vohtori_runtime_free(VohtoriRuntime *runtime);
+
+// Evaluates every line of `source` and returns the value of the last one,
+// or null if evaluation failed, see `vohtori_last_error`.
+//
+// # Safety
+//
+// `runtime` must be a live runtime and `source` a NUL terminated string.
+VohtoriValue *vohtori_eval(VohtoriRuntime *runtime, const char *source);
+
+// The message of the last failed evaluation, or null if it succeeded. The
+// string is owned by the runtime and valid until the next evaluation.
+//
+// # Safety
+//
+// `runtime` must be a live runtime.
+const char *vohtori_last_error(const VohtoriRuntime *runtime);
+
+// # Safety
+//
+// `value` must be returned by `vohtori_eval` and not be used afterwards.
+// Null is ignored.
+void vohtori_value_free(VohtoriValue *value);
+
+// # Safety
+//
+// `value` must point to a live value, as must every `value` below.
+VohtoriType vohtori_value_type(const VohtoriValue *value);
+
+// The value of a number, or 0 for any other type.
+//
+// # Safety
+//
+// See `vohtori_value_type`.
+int64_t vohtori_value_number(const VohtoriValue *value);
+
+// The UTF-8 bytes of a string, which are not NUL terminated, with their
+// count stored in `len`. Null for any other type.
+//
+// # Safety
+//
+// See `vohtori_value_type`, `len` must be writable.
+const char *vohtori_value_string(const VohtoriValue *value, size_t *len);
+
+// The number of elements of an array or entries of a map, 0 for any other
+// type.
+//
+// # Safety
+//
+// See `vohtori_value_type`.
+size_t vohtori_value_len(const VohtoriValue *value);
+
+// The element of an array at `index`, or null if out of bounds.
+//
+// # Safety
+//
+// See `vohtori_value_type`.
+const VohtoriValue *vohtori_value_element(const VohtoriValue *value, size_t index);
+
+// The key of the map entry at `index` in key order, or null if out of
+// bounds.
+//
+// # Safety
+//
+// See `vohtori_value_type`.
+const VohtoriValue *vohtori_value_key(const VohtoriValue *value, size_t index);
+
+// The value of the map entry at `index` in key order, or null if out of
+// bounds.
+//
+// # Safety
+//
+// See `vohtori_value_type`.
+const VohtoriValue *vohtori_value_entry(const VohtoriValue *value, size_t index);
+
+// Formats a value as the CLI prints it. The string is owned by the caller
+// and freed with `vohtori_string_free`.
+//
+// # Safety
+//
+// See `vohtori_value_type`.
+char *vohtori_value_display(const VohtoriValue *value);
+
+// # Safety
+//
+// `string` must come from `vohtori_value_display` and not be used
+// afterwards. Null is ignored.
+void vohtori_string_free(char *string);
+
+#endif /* VOHTORI_H */
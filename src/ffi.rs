@@ -0,0 +1,289 @@
+//! A C interface for embedding the interpreter, declared in
+//! `include/vohtori.h`.
+//!
+//! Values returned by `vohtori_eval` are owned by the caller and freed with
+//! `vohtori_value_free`. Values reached by walking them are borrowed and stay
+//! valid until their root is freed.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::{eval_str, Runtime, Value};
+
+/// A runtime together with the message of the last failed evaluation.
+pub struct VohtoriRuntime {
+    runtime: Runtime,
+    last_error: Option<CString>,
+}
+
+#[repr(C)]
+pub enum VohtoriType {
+    Array,
+    Map,
+    Number,
+    String,
+    Function,
+}
+
+/// A value handed out to C. Elements and map entries are only taken out of
+/// it the first time they are asked for, and are then kept so that they can
+/// be borrowed until the root is freed. Walking a value never stores more
+/// than what was read, so a large range stays a range.
+pub struct VohtoriValue {
+    value: Value,
+    children: RefCell<HashMap<Child, Box<VohtoriValue>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Child {
+    Element(usize),
+    Key(usize),
+    Entry(usize),
+}
+
+impl VohtoriValue {
+    fn new(value: Value) -> Self {
+        Self {
+            value,
+            children: RefCell::default(),
+        }
+    }
+
+    /// The child taken out of this value by `get`, or null if there is none.
+    /// Being boxed, it keeps its address while more children are added.
+    fn child(&self, child: Child, get: impl FnOnce(&Value) -> Option<Value>) -> *const Self {
+        let mut children = self.children.borrow_mut();
+
+        if let Some(value) = children.get(&child) {
+            return &**value;
+        }
+
+        match get(&self.value) {
+            Some(value) => {
+                let value = Box::new(Self::new(value));
+                let ptr: *const Self = &*value;
+                children.insert(child, value);
+                ptr
+            }
+            None => ptr::null(),
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn vohtori_runtime_new() -> *mut VohtoriRuntime {
+    Box::into_raw(Box::new(VohtoriRuntime {
+        runtime: Runtime::new(),
+        last_error: None,
+    }))
+}
+
+/// # Safety
+///
+/// `runtime` must come from `vohtori_runtime_new` and not be used afterwards.
+/// Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_runtime_free(runtime: *mut VohtoriRuntime) {
+    if !runtime.is_null() {
+        drop(Box::from_raw(runtime));
+    }
+}
+
+/// Evaluates every line of `source` and returns the value of the last one,
+/// or null if evaluation failed, see `vohtori_last_error`.
+///
+/// # Safety
+///
+/// `runtime` must be a live runtime and `source` a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_eval(
+    runtime: *mut VohtoriRuntime,
+    source: *const c_char,
+) -> *mut VohtoriValue {
+    let runtime = &mut *runtime;
+
+    let result = match CStr::from_ptr(source).to_str() {
+        Ok(source) => panic::catch_unwind(AssertUnwindSafe(|| {
+            eval_str(&mut runtime.runtime, source).map_err(|e| e.to_string())
+        }))
+        .unwrap_or_else(|_| Err("Evaluation panicked".to_owned())),
+        Err(_) => Err("Source is not valid UTF-8".to_owned()),
+    };
+
+    match result {
+        Ok(value) => {
+            runtime.last_error = None;
+            Box::into_raw(Box::new(VohtoriValue::new(value)))
+        }
+        Err(message) => {
+            runtime.last_error = CString::new(message.replace('\0', "")).ok();
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The message of the last failed evaluation, or null if it succeeded. The
+/// string is owned by the runtime and valid until the next evaluation.
+///
+/// # Safety
+///
+/// `runtime` must be a live runtime.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_last_error(runtime: *const VohtoriRuntime) -> *const c_char {
+    (*runtime)
+        .last_error
+        .as_ref()
+        .map_or(ptr::null(), |message| message.as_ptr())
+}
+
+/// # Safety
+///
+/// `value` must be returned by `vohtori_eval` and not be used afterwards.
+/// Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_free(value: *mut VohtoriValue) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// # Safety
+///
+/// `value` must point to a live value, as must every `value` below.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_type(value: *const VohtoriValue) -> VohtoriType {
+    match &(*value).value {
+        Value::Array(_) => VohtoriType::Array,
+        Value::Map(_) => VohtoriType::Map,
+        Value::Number(_) => VohtoriType::Number,
+        Value::String(_) => VohtoriType::String,
+        Value::Function(_) => VohtoriType::Function,
+    }
+}
+
+/// The value of a number, or 0 for any other type.
+///
+/// # Safety
+///
+/// See `vohtori_value_type`.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_number(value: *const VohtoriValue) -> i64 {
+    match &(*value).value {
+        Value::Number(number) => number.value as i64,
+        _ => 0,
+    }
+}
+
+/// The UTF-8 bytes of a string, which are not NUL terminated, with their
+/// count stored in `len`. Null for any other type.
+///
+/// # Safety
+///
+/// See `vohtori_value_type`, `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_string(
+    value: *const VohtoriValue,
+    len: *mut usize,
+) -> *const c_char {
+    match &(*value).value {
+        Value::String(string) => {
+            *len = string.len();
+            string.as_ptr().cast()
+        }
+        _ => ptr::null(),
+    }
+}
+
+/// The number of elements of an array or entries of a map, 0 for any other
+/// type.
+///
+/// # Safety
+///
+/// See `vohtori_value_type`.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_len(value: *const VohtoriValue) -> usize {
+    match &(*value).value {
+        Value::Array(arr) => arr.len(),
+        Value::Map(map) => map.len(),
+        _ => 0,
+    }
+}
+
+/// The element of an array at `index`, or null if out of bounds.
+///
+/// # Safety
+///
+/// See `vohtori_value_type`.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_element(
+    value: *const VohtoriValue,
+    index: usize,
+) -> *const VohtoriValue {
+    (*value).child(Child::Element(index), |value| match value {
+        Value::Array(arr) => arr.get(index),
+        _ => None,
+    })
+}
+
+/// The key of the map entry at `index` in key order, or null if out of
+/// bounds.
+///
+/// # Safety
+///
+/// See `vohtori_value_type`.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_key(
+    value: *const VohtoriValue,
+    index: usize,
+) -> *const VohtoriValue {
+    (*value).child(Child::Key(index), |value| match value {
+        Value::Map(map) => map.entries().keys().nth(index).cloned(),
+        _ => None,
+    })
+}
+
+/// The value of the map entry at `index` in key order, or null if out of
+/// bounds.
+///
+/// # Safety
+///
+/// See `vohtori_value_type`.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_entry(
+    value: *const VohtoriValue,
+    index: usize,
+) -> *const VohtoriValue {
+    (*value).child(Child::Entry(index), |value| match value {
+        Value::Map(map) => map.entries().values().nth(index).cloned(),
+        _ => None,
+    })
+}
+
+/// Formats a value as the CLI prints it. The string is owned by the caller
+/// and freed with `vohtori_string_free`.
+///
+/// # Safety
+///
+/// See `vohtori_value_type`.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_value_display(value: *const VohtoriValue) -> *mut c_char {
+    CString::new((*value).value.to_string().replace('\0', ""))
+        .unwrap()
+        .into_raw()
+}
+
+/// # Safety
+///
+/// `string` must come from `vohtori_value_display` and not be used
+/// afterwards. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn vohtori_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
@@ -0,0 +1,152 @@
+use std::io::Read;
+
+use clap::ValueEnum;
+
+use vohtori::{Number, RuntimeError, Value};
+
+/// How the data read from standard input is turned into the `stdin` value.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A table of whitespace separated fields, one row per line
+    #[default]
+    Ints,
+    /// Every line as a string
+    Lines,
+    /// A grid of one character strings, one row per line
+    Chars,
+    /// A table of comma separated fields, double quotes escape commas
+    Csv,
+    /// Blank line separated sections, each a table like `ints`
+    Sections,
+    /// Every integer appearing in a line, one row per line
+    Extract,
+}
+
+pub fn read(mut reader: impl Read, format: Format) -> Result<Value, RuntimeError> {
+    let mut input = String::new();
+
+    reader
+        .read_to_string(&mut input)
+        .map_err(|e| RuntimeError::Input(e.to_string()))?;
+
+    Ok(parse(&input, format))
+}
+
+pub fn parse(input: &str, format: Format) -> Value {
+    match format {
+        Format::Ints => table(input),
+        Format::Lines => array(input.lines().map(|l| Value::String(l.to_owned()))),
+        Format::Chars => array(
+            input
+                .lines()
+                .map(|l| array(l.chars().map(|c| Value::String(c.to_string())))),
+        ),
+        Format::Csv => array(
+            input
+                .lines()
+                .map(|l| array(split_csv(l).into_iter().map(|f| field(&f)))),
+        ),
+        Format::Sections => array(sections(input).into_iter().map(|s| table(&s))),
+        Format::Extract => array(
+            input
+                .lines()
+                .map(|l| array(integers(l).into_iter().map(field))),
+        ),
+    }
+}
+
+/// Fields that are not valid integers are kept as strings instead of
+/// aborting the whole program.
+fn field(field: &str) -> Value {
+    match field.parse() {
+        Ok(value) => Value::Number(Number { value }),
+        Err(_) => Value::String(field.to_owned()),
+    }
+}
+
+fn array(values: impl Iterator<Item = Value>) -> Value {
+    Value::Array(values.collect())
+}
+
+fn table(input: &str) -> Value {
+    array(
+        input
+            .lines()
+            .map(|l| array(l.split_whitespace().map(field))),
+    )
+}
+
+fn sections(input: &str) -> Vec<String> {
+    let mut sections = vec![];
+    let mut section = String::new();
+
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            if !section.is_empty() {
+                sections.push(std::mem::take(&mut section));
+            }
+        } else {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+
+    if !section.is_empty() {
+        sections.push(section);
+    }
+
+    sections
+}
+
+fn split_csv(line: &str) -> Vec<String> {
+    if line.is_empty() {
+        return vec![];
+    }
+
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+
+    fields.push(field);
+
+    fields.iter().map(|f| f.trim().to_owned()).collect()
+}
+
+/// A `-` directly before the digits is a sign unless it follows another
+/// digit, so ranges like `3-5` read as two positive numbers.
+fn integers(line: &str) -> Vec<&str> {
+    let bytes = line.as_bytes();
+    let mut integers = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i].is_ascii_digit() {
+            let negative =
+                i > 0 && bytes[i - 1] == b'-' && !(i > 1 && bytes[i - 2].is_ascii_digit());
+            let start = if negative { i - 1 } else { i };
+
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+
+            integers.push(&line[start..i]);
+        } else {
+            i += 1;
+        }
+    }
+
+    integers
+}
@@ -1,8 +1,10 @@
 use std::{
-    io::{BufRead, Lines, Stdin, StdinLock},
+    io::{BufRead, Lines},
     str::Chars,
 };
 
+use crate::parser::error::ParserError;
+
 static SYNTAX_TOKENS: &str = "{}[]();:$";
 
 static OPERATOR_CHARS: &str = "+-*/=@&%^.#|&";
@@ -50,15 +52,16 @@ impl<T: BufRead> SourceCursor<T> {
         }
     }
 
-    pub fn tokenize_line(&mut self) -> Option<Vec<Token>> {
+    pub fn tokenize_line(&mut self) -> Option<Result<Vec<Token>, ParserError>> {
         self.lines.next().map(|l| {
             let str = l.unwrap();
             let mut line = Line::new(&str);
 
-            let mut tokens: Vec<Token> = std::iter::from_fn(move || line.advance_token()).collect();
+            let mut tokens = std::iter::from_fn(move || line.advance_token())
+                .collect::<Result<Vec<Token>, _>>()?;
             tokens.reverse();
 
-            tokens
+            Ok(tokens)
         })
     }
 }
@@ -78,21 +81,13 @@ impl<'a> Line<'a> {
         self.chars.clone().next()
     }
 
-    fn second(&self) -> Option<char> {
-        let mut chars = self.chars.clone();
-
-        chars.next();
-
-        chars.next()
-    }
-
     fn discard_whitespaces(&mut self) {
         while self.first().is_some_and(|c| c.is_whitespace()) {
             self.consume();
         }
     }
 
-    pub fn advance_token(&mut self) -> Option<Token> {
+    pub fn advance_token(&mut self) -> Option<Result<Token, ParserError>> {
         self.discard_whitespaces();
 
         let mut token_content = String::new();
@@ -100,7 +95,7 @@ impl<'a> Line<'a> {
         let mode = match self.first()? {
             c if c.is_alphabetic() || c == '_' => LexerMode::Identifier,
             c if c.is_ascii_digit() => LexerMode::Number,
-            c if c == '"' => {
+            '"' => {
                 self.consume();
                 LexerMode::Literal
             }
@@ -108,12 +103,15 @@ impl<'a> Line<'a> {
             c if SYNTAX_TOKENS.contains(c) => {
                 self.consume();
 
-                return Some(Token {
+                return Some(Ok(Token {
                     token_type: TokenType::SyntaxToken,
                     content: c.to_string(),
-                });
+                }));
+            }
+            c => {
+                self.consume();
+                return Some(Err(ParserError::InvalidCharacter(c)));
             }
-            _ => panic!("Invalid character"),
         };
 
         while let Some(c) = self.first() {
@@ -123,19 +121,19 @@ impl<'a> Line<'a> {
                         token_content.push(c);
                         self.consume();
                     } else {
-                        return Some(Token {
+                        return Some(Ok(Token {
                             token_type: TokenType::Identifier,
                             content: token_content,
-                        });
+                        }));
                     }
                 }
                 LexerMode::Literal => {
                     if c == '"' {
                         self.consume();
-                        return Some(Token {
+                        return Some(Ok(Token {
                             token_type: TokenType::Literal(LiteralType::String),
                             content: token_content,
-                        });
+                        }));
                     } else {
                         token_content.push(c);
                         self.consume();
@@ -146,10 +144,10 @@ impl<'a> Line<'a> {
                         token_content.push(c);
                         self.consume();
                     } else {
-                        return Some(Token {
+                        return Some(Ok(Token {
                             token_type: TokenType::Literal(LiteralType::Number),
                             content: token_content,
-                        });
+                        }));
                     }
                 }
                 LexerMode::Operator => {
@@ -157,10 +155,10 @@ impl<'a> Line<'a> {
                         token_content.push(c);
                         self.consume();
                     } else {
-                        return Some(Token {
+                        return Some(Ok(Token {
                             token_type: TokenType::Operator,
                             content: token_content,
-                        });
+                        }));
                     }
                 }
             }
@@ -172,9 +170,9 @@ impl<'a> Line<'a> {
             LexerMode::Operator => TokenType::Operator,
         };
 
-        Some(Token {
+        Some(Ok(Token {
             token_type,
             content: token_content,
-        })
+        }))
     }
 }
@@ -0,0 +1,80 @@
+//! An array language evaluated one line at a time.
+//!
+//! ```
+//! let mut runtime = vohtori::Runtime::new();
+//! vohtori::eval_str(&mut runtime, "xs = [1 2 3]")?;
+//!
+//! let total = vohtori::eval_str(&mut runtime, "+ xs")?;
+//! assert_eq!(total.to_string(), "6");
+//! # Ok::<(), vohtori::Error>(())
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+mod ffi;
+mod lexer;
+mod library;
+mod parser;
+mod runtime;
+
+use std::fmt::Display;
+
+pub use library::{builtin, Builtin, BUILTINS};
+pub use parser::{error::ParserError, Expr, Number, Parser};
+pub use runtime::{
+    error::RuntimeError,
+    function::Function,
+    limits::{Limit, Limits},
+    Array, Map, Runtime, Value,
+};
+
+/// Evaluation recurses on the native stack. A thread with less room than
+/// this reports `RuntimeError::RecursionLimit` before reaching the default
+/// `Limits::max_depth` of nested calls, rather than overflowing.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum Error {
+    Parser(ParserError),
+    Runtime(RuntimeError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parser(err) => write!(f, "{}", err),
+            Error::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ParserError> for Error {
+    fn from(value: ParserError) -> Self {
+        Self::Parser(value)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(value: RuntimeError) -> Self {
+        Self::Runtime(value)
+    }
+}
+
+/// Evaluates every line of `source`, stopping at the first error, and returns
+/// the value of the last one. A source without expressions evaluates to an
+/// empty array. The whole source is one evaluation, see `Runtime::start`.
+pub fn eval_str(runtime: &mut Runtime, source: &str) -> Result<Value, Error> {
+    let mut parser = Parser::new(source.as_bytes());
+    let mut value = Value::Array(Array::default());
+
+    runtime.start();
+
+    while let Some(expr) = parser.parse_expr() {
+        value = runtime.eval_expr(&expr?)?;
+    }
+
+    Ok(value)
+}
@@ -0,0 +1,106 @@
+use crate::{
+    parser::Number,
+    runtime::{
+        error::{RuntimeError, TypeMismatchError},
+        Array, Value,
+    },
+};
+
+use super::unpack;
+
+/// `each: [array f]`, applies `f` to every element.
+pub fn each(
+    arg: Value,
+    f: impl FnMut(Value) -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    Ok(Value::Array(
+        arg.into_array().iter().map(f).collect::<Result<_, _>>()?,
+    ))
+}
+
+/// `fold: [array init f]`, `f` is called with the accumulator as `$0` and
+/// the element as `$1`. Elements are read one at a time, so folding over a
+/// range never stores it.
+pub fn fold(
+    arg: Value,
+    mut f: impl FnMut(Value, Value) -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let [array, init] = unpack(arg)?;
+
+    array.into_array().iter().try_fold(init, &mut f)
+}
+
+/// `scan: [array init f]`, like `fold` but returns every intermediate
+/// accumulator.
+pub fn scan(
+    arg: Value,
+    mut f: impl FnMut(Value, Value) -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let [array, init] = unpack(arg)?;
+    let mut acc = init;
+
+    Ok(Value::Array(
+        array
+            .into_array()
+            .iter()
+            .map(|v| {
+                acc = f(acc.clone(), v)?;
+                Ok(acc.clone())
+            })
+            .collect::<Result<_, RuntimeError>>()?,
+    ))
+}
+
+/// `iterate: [init n f]`, returns `init` followed by `n` successive
+/// applications of `f`.
+pub fn iterate(
+    arg: Value,
+    mut f: impl FnMut(Value) -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let [init, n] = unpack(arg)?;
+    let n = match n {
+        Value::Number(Number { value }) => value.max(0) as usize,
+        _ => return Err(TypeMismatchError::new("number", n.type_name()).into()),
+    };
+
+    let mut value = vec![init];
+
+    for _ in 0..n {
+        let next = f(value.last().unwrap().clone())?;
+        value.push(next);
+    }
+
+    Ok(Value::Array(Array::new(value)))
+}
+
+/// `until: [init pred f]`, `step` returns `None` once the predicate holds.
+pub fn until(
+    init: Value,
+    mut step: impl FnMut(Value) -> Result<Option<Value>, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let mut value = init;
+
+    while let Some(next) = step(value.clone())? {
+        value = next;
+    }
+
+    Ok(value)
+}
+
+/// `fixpoint: [init f]`, applies `f` until the value stops changing.
+pub fn fixpoint(
+    init: Value,
+    mut f: impl FnMut(Value) -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let mut value = init;
+
+    loop {
+        let next = f(value.clone())?;
+
+        if next == value {
+            return Ok(value);
+        }
+
+        value = next;
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use crate::runtime::{
+    error::{RuntimeError, TypeMismatchError},
+    Array, Map, Value,
+};
+
+use super::unpack;
+
+/// `get: [map key]`
+pub fn get(arg: Value) -> Result<Value, RuntimeError> {
+    let [map, key] = unpack(arg)?;
+
+    into_map(map)?
+        .entries()
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| RuntimeError::KeyNotFound(key.to_string()))
+}
+
+/// `insert: [map key value]`, returns the updated map.
+pub fn insert(arg: Value) -> Result<Value, RuntimeError> {
+    let [map, key, value] = unpack(arg)?;
+    let mut map = into_map(map)?;
+
+    map.make_mut().insert(key, value);
+
+    Ok(Value::Map(map))
+}
+
+pub fn keys(arg: Value) -> Result<Value, RuntimeError> {
+    Ok(Value::Array(
+        into_map(arg)?.into_entries().into_keys().collect(),
+    ))
+}
+
+pub fn values(arg: Value) -> Result<Value, RuntimeError> {
+    Ok(Value::Array(
+        into_map(arg)?.into_entries().into_values().collect(),
+    ))
+}
+
+/// Groups the elements of an array into a map from the value `key` returns
+/// for them to the elements sharing that value, in their original order.
+pub fn group_by(
+    arg: Value,
+    mut key: impl FnMut(Value) -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let mut groups: BTreeMap<Value, Value> = BTreeMap::new();
+
+    for v in arg.into_array().into_vec()? {
+        let group = groups
+            .entry(key(v.clone())?)
+            .or_insert_with(|| Value::Array(Array::default()));
+
+        if let Value::Array(group) = group {
+            group.make_mut()?.push(v);
+        }
+    }
+
+    Ok(Value::Map(Map::new(groups)))
+}
+
+fn into_map(value: Value) -> Result<Map, RuntimeError> {
+    match value {
+        Value::Map(map) => Ok(map),
+        _ => Err(TypeMismatchError::new("map", value.type_name()).into()),
+    }
+}
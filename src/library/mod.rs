@@ -1,26 +1,141 @@
-use crate::runtime::{Value, Array};
+use crate::{
+    parser::Number,
+    runtime::{
+        error::{ArgumentCountError, RuntimeError, TypeMismatchError},
+        Array, Value,
+    },
+};
 
-pub fn index(arg: Value) -> Value {
+mod functional;
+mod map;
+pub use functional::*;
+pub use map::*;
+
+/// A function provided by the runtime, with the documentation shown by the
+/// REPL's `:help`.
+pub struct Builtin {
+    pub name: &'static str,
+    pub signature: &'static str,
+    pub doc: &'static str,
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "idx",
+        signature: "idx: array",
+        doc: "Replaces every non-zero number with its index, keeping zeros.",
+    },
+    Builtin {
+        name: "take",
+        signature: "take: [array n]",
+        doc: "The first `n` elements, without computing the rest of a range.",
+    },
+    Builtin {
+        name: "get",
+        signature: "get: [map key]",
+        doc: "The value stored under `key`.",
+    },
+    Builtin {
+        name: "insert",
+        signature: "insert: [map key value]",
+        doc: "The map with `key` set to `value`.",
+    },
+    Builtin {
+        name: "keys",
+        signature: "keys: map",
+        doc: "The keys of a map in order.",
+    },
+    Builtin {
+        name: "values",
+        signature: "values: map",
+        doc: "The values of a map in the order of their keys.",
+    },
+    Builtin {
+        name: "group_by",
+        signature: "group_by: [array f]",
+        doc: "Groups the elements into a map from what `f` returns for them.",
+    },
+    Builtin {
+        name: "each",
+        signature: "each: [array f]",
+        doc: "Applies `f` to every element.",
+    },
+    Builtin {
+        name: "fold",
+        signature: "fold: [array init f]",
+        doc: "Combines the elements, `f` gets the accumulator as `$0` and the element as `$1`.",
+    },
+    Builtin {
+        name: "scan",
+        signature: "scan: [array init f]",
+        doc: "Like `fold` but returns every intermediate accumulator.",
+    },
+    Builtin {
+        name: "iterate",
+        signature: "iterate: [init n f]",
+        doc: "`init` followed by `n` successive applications of `f`.",
+    },
+    Builtin {
+        name: "until",
+        signature: "until: [init pred f]",
+        doc: "Applies `f` until `pred` holds for the value.",
+    },
+    Builtin {
+        name: "fixpoint",
+        signature: "fixpoint: [init f]",
+        doc: "Applies `f` until the value stops changing.",
+    },
+];
+
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+pub fn index(arg: Value) -> Result<Value, RuntimeError> {
     if let Value::Array(mut arr) = arg {
-        index_arr(&mut arr);
+        index_arr(&mut arr)?;
 
-        Value::Array(arr)
+        Ok(Value::Array(arr))
     } else {
-        arg
+        Ok(arg)
     }
 }
 
-fn index_arr(arg: &mut Array) {
-    arg.value.iter_mut().enumerate().for_each(|(i, n)| {
+/// `take: [array n]`, all elements if there are fewer than `n`.
+pub fn take(arg: Value) -> Result<Value, RuntimeError> {
+    let [array, n] = unpack(arg)?;
+    let n = match n {
+        Value::Number(Number { value }) => value.max(0) as usize,
+        _ => return Err(TypeMismatchError::new("number", n.type_name()).into()),
+    };
+
+    Ok(Value::Array(array.into_array().take(n)))
+}
+
+fn index_arr(arg: &mut Array) -> Result<(), RuntimeError> {
+    for (i, n) in arg.make_mut()?.iter_mut().enumerate() {
         match n {
-            Value::Array(arr) => {
-                index_arr(arr)
-            },
+            Value::Array(arr) => index_arr(arr)?,
+            Value::Map(_) | Value::String(_) | Value::Function(_) => (),
             Value::Number(num) => {
                 if num.value != 0 {
                     num.value = i as isize;
                 }
             }
         };
-    });
+    }
+
+    Ok(())
+}
+
+/// The `N` elements of an argument list, checking its length before any
+/// elements are unpacked.
+fn unpack<const N: usize>(value: Value) -> Result<[Value; N], RuntimeError> {
+    let array = value.into_array();
+
+    if array.len() != N {
+        return Err(ArgumentCountError::new(N, array.len()).into());
+    }
+
+    Ok(array.into_vec()?.try_into().unwrap())
 }
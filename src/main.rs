@@ -1,21 +1,26 @@
-#![feature(result_option_inspect, is_some_and, bigint_helper_methods)]
+mod input;
+mod output;
+mod repl;
+mod workspace;
 
 #[cfg(test)]
-mod tests;
-
-mod lexer;
-mod library;
-mod parser;
-mod runtime;
-
-use std::{fs::File, io::{BufReader, Read}, path::PathBuf};
+mod tests {
+    mod input;
+    mod output;
+    mod repl;
+    mod workspace;
+}
 
-use lexer::SourceCursor;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    time::Duration,
+};
 
-use parser::{Parser, Number};
-use runtime::{Value, Array};
+use vohtori::{Limits, Parser, Runtime, RuntimeError, STACK_SIZE};
 
-use crate::runtime::Runtime;
+use self::output::{Boxed, Formatter};
 
 use clap::Parser as ClapParser;
 
@@ -27,97 +32,159 @@ struct Args {
 
     #[arg(short, long)]
     parser: bool,
+
+    /// How the input data is parsed into `stdin`
+    #[arg(short, long, value_enum, default_value_t)]
+    format: input::Format,
+
+    /// How results are written to standard output [default: plain, or boxed
+    /// in interactive mode]
+    #[arg(short, long, value_enum)]
+    output: Option<output::Output>,
+
+    /// Read the input data from a file instead of standard input
+    #[arg(long, value_name = "FILE")]
     input: Option<PathBuf>,
+
+    /// Evaluate an expression instead of a script, may be repeated
+    #[arg(
+        short,
+        long = "eval",
+        value_name = "EXPR",
+        conflicts_with_all = ["script", "interactive"]
+    )]
+    eval: Vec<String>,
+
+    /// Restore the variables saved in a workspace before running
+    #[arg(long, value_name = "FILE")]
+    load_workspace: Option<PathBuf>,
+
+    /// Save the variables to a workspace once done
+    #[arg(long, value_name = "FILE")]
+    save_workspace: Option<PathBuf>,
+
+    /// Abort an evaluation after this many steps
+    #[arg(long, value_name = "N")]
+    max_steps: Option<u64>,
+
+    /// Abort an evaluation once it has created this many array elements
+    #[arg(long, value_name = "N")]
+    max_elements: Option<usize>,
+
+    /// Abort an evaluation after this many milliseconds
+    #[arg(long, value_name = "MS")]
+    timeout: Option<u64>,
+
+    // Not called `input`, which would clash with the `--input` option.
+    /// The script to run
+    script: Option<PathBuf>,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let mut stdin = std::io::stdin().lock();
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(args))
+        .unwrap()
+        .join()
+        .unwrap();
+}
 
+fn run(args: Args) {
     let mut runtime = Runtime::new();
+    runtime.set_limits(Limits {
+        max_steps: args.max_steps,
+        max_elements: args.max_elements,
+        timeout: args.timeout.map(Duration::from_millis),
+        ..Default::default()
+    });
+
+    let format = args.format;
+
+    // The input is only read once a script refers to `stdin`, so scripts that
+    // do not need it never block waiting for EOF.
+    if let Some(path) = &args.input {
+        let file = File::open(path).unwrap_or_else(|e| {
+            eprintln!("Failed to open {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+
+        runtime.push_lazy_var("stdin", move || input::read(file, format));
+    } else if !args.interactive {
+        runtime.push_lazy_var("stdin", move || input::read(std::io::stdin(), format));
+    }
 
-    if args.interactive {
-        let source = SourceCursor::new(stdin);
-
-        let mut parser = Parser::new(source);
-
-        while let Some(expr) = parser.parse_expr() {
-            if args.parser {
-                eprintln!("PARSER OUTPUT:");
-                eprintln!("{:#?}", expr);
-                eprintln!();
-            }
-
-            match expr {
-                Ok(expr) => {
-                    let value = runtime.eval_expr(expr, None);
+    if let Some(path) = &args.load_workspace {
+        if let Err(e) = workspace::load_file(&mut runtime, path) {
+            eprintln!("Failed to load {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
 
-                    print!("  = ");
-                    pprint(&value);
-                    println!();
-                }
-                Err(e) => {
-                    println!("{:?}", e);
-                }
-            }
+    if args.interactive {
+        let formatter: Box<dyn Formatter> = match args.output {
+            Some(output) => output.formatter(),
+            None => Box::new(Boxed {
+                max_width: terminal_width(),
+                ..Default::default()
+            }),
+        };
+
+        repl::run(&mut runtime, formatter.as_ref(), args.parser);
+    } else if !args.eval.is_empty() {
+        let formatter = args.output.unwrap_or_default().formatter();
+
+        for source in &args.eval {
+            eval_source(&mut runtime, source.as_bytes(), formatter.as_ref());
         }
-    } else if let Some(path) = args.input {
+    } else if let Some(path) = args.script {
+        let formatter = args.output.unwrap_or_default().formatter();
         let file = File::open(path).unwrap();
-        let source = SourceCursor::new(BufReader::new(file));
-
-        let mut parser = Parser::new(source);
-
-        let mut input = String::new();
-        stdin.read_to_string(&mut input).unwrap();
-
-        runtime.push_var("stdin", parse_list(input));
-
 
-        while let Some(expr) = parser.parse_expr() {
-            match expr {
-                Ok(expr) => {
-                    let value = runtime.eval_expr(expr, None);
+        eval_source(&mut runtime, BufReader::new(file), formatter.as_ref());
+    }
 
-                    pprint(&value);
-                    println!();
-                }
-                Err(e) => {
-                    println!("{:?}", e);
+    if let Some(path) = &args.save_workspace {
+        match workspace::save_file(&runtime, path) {
+            Ok(skipped) => {
+                for name in skipped {
+                    eprintln!("Skipped {}, only builtin functions can be saved", name);
                 }
             }
+            Err(e) => eprintln!("Failed to save {}: {}", path.display(), e),
         }
     }
 }
 
-fn pprint(value: &Value) {
-    match value {
-        Value::Array(array) => {
-            print!("[");
-
-            let len = array.value.len();
+/// Boxed output leaves room for the `  = ` prefix of the result. `COLUMNS`
+/// is only used when standard output is not a terminal.
+fn terminal_width() -> usize {
+    repl::terminal::columns()
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse::<usize>().ok())
+        .unwrap_or(80)
+        .saturating_sub(4)
+}
 
-            if len > 0 {
-                for i in 0..(len - 1) {
-                    pprint(&array.value[i]);
-                    print!(" ");
+/// Evaluates and prints every expression in `source`, all within one budget
+/// of the limits. An interrupt stops the rest of the source.
+fn eval_source(runtime: &mut Runtime, source: impl BufRead, formatter: &dyn Formatter) {
+    let mut parser = Parser::new(source);
+    runtime.start();
+
+    while let Some(expr) = parser.parse_expr() {
+        match expr {
+            Ok(expr) => match runtime.eval_expr(&expr) {
+                Ok(value) => formatter.write(&value, &mut std::io::stdout().lock()).unwrap(),
+                Err(RuntimeError::Interrupted) => {
+                    println!("{}", RuntimeError::Interrupted);
+                    return;
                 }
-
-                pprint(&array.value[len - 1]);
+                Err(e) => println!("{}", e),
+            },
+            Err(e) => {
+                println!("{:?}", e);
             }
-
-            print!("]");
         }
-        Value::Number(number) => print!("{}", number.value),
     }
 }
-
-fn parse_list(input: String) -> Value {
-    Value::Array(Array {
-        value: input.lines().map(|l| {
-            Value::Array(Array {
-                value: l.split_whitespace().map(|v| Value::Number(Number {value: v.parse().unwrap() })).collect(),
-            })
-        }).collect(),
-    })
-}
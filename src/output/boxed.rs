@@ -0,0 +1,177 @@
+use std::io::{self, Write};
+
+use vohtori::{Array, Value};
+
+use super::{align, matrix, Formatter};
+
+/// APL style display where every array is drawn inside a box, with matrices
+/// as aligned columns. Arrays longer than `max_elements` and lines wider than
+/// `max_width` are elided with `...`.
+pub struct Boxed {
+    pub max_width: usize,
+    pub max_elements: usize,
+}
+
+impl Default for Boxed {
+    fn default() -> Self {
+        Self {
+            max_width: 80,
+            max_elements: 100,
+        }
+    }
+}
+
+impl Formatter for Boxed {
+    fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        for line in self.render(value).lines {
+            writeln!(out, "{}", truncate(&line, self.max_width))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A rectangle of text, every line is padded to the same width.
+struct Block {
+    width: usize,
+    lines: Vec<String>,
+}
+
+impl Block {
+    fn new(lines: Vec<String>) -> Self {
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        Self {
+            lines: lines.into_iter().map(|l| pad(&l, width)).collect(),
+            width,
+        }
+    }
+
+    fn text(text: String) -> Self {
+        Self::new(vec![text])
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Places blocks next to each other, aligned at the top.
+    fn join(blocks: Vec<Block>, separator: &str) -> Self {
+        let height = blocks.iter().map(Block::height).max().unwrap_or(0);
+
+        let lines = (0..height)
+            .map(|i| {
+                blocks
+                    .iter()
+                    .map(|b| match b.lines.get(i) {
+                        Some(line) => line.clone(),
+                        None => " ".repeat(b.width),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(separator)
+            })
+            .collect();
+
+        Self::new(lines)
+    }
+
+    fn stack(blocks: Vec<Block>) -> Self {
+        Self::new(blocks.into_iter().flat_map(|b| b.lines).collect())
+    }
+
+    fn boxed(self) -> Self {
+        let border = "─".repeat(self.width);
+        let mut lines = vec![format!("┌{}┐", border)];
+
+        lines.extend(self.lines.into_iter().map(|l| format!("│{}│", l)));
+        lines.push(format!("└{}┘", border));
+
+        Self::new(lines)
+    }
+}
+
+impl Boxed {
+    fn render(&self, value: &Value) -> Block {
+        match value {
+            Value::Array(array) if array.is_empty() => {
+                Block::new(vec!["┌┐".to_owned(), "└┘".to_owned()])
+            }
+            Value::Array(array) => match matrix(value) {
+                Some(rows) => self.render_matrix(rows),
+                _ => {
+                    let mut blocks: Vec<Block> = array
+                        .iter()
+                        .take(self.max_elements)
+                        .map(|v| self.render(&v))
+                        .collect();
+
+                    if array.len() > self.max_elements {
+                        blocks.push(Block::text("...".to_owned()));
+                    }
+
+                    Block::join(blocks, " ").boxed()
+                }
+            },
+            Value::Map(map) => {
+                let mut rows: Vec<Block> = map
+                    .entries()
+                    .iter()
+                    .take(self.max_elements)
+                    .map(|(k, v)| Block::join(vec![self.render(k), self.render(v)], " │ "))
+                    .collect();
+
+                if map.len() > self.max_elements {
+                    rows.push(Block::text("...".to_owned()));
+                }
+
+                Block::stack(rows).boxed()
+            }
+            _ => Block::text(value.to_string()),
+        }
+    }
+
+    /// Only the rows and columns that are shown are read.
+    fn render_matrix(&self, rows: &Array) -> Block {
+        let elided = rows.len() > self.max_elements;
+
+        let mut cells: Vec<Vec<String>> = rows
+            .iter()
+            .take(self.max_elements)
+            .map(|row| {
+                let row = row.into_array();
+                let mut cells: Vec<String> = row
+                    .iter()
+                    .take(self.max_elements)
+                    .map(|v| v.to_string())
+                    .collect();
+
+                if row.len() > self.max_elements {
+                    cells.push("...".to_owned());
+                }
+
+                cells
+            })
+            .collect();
+
+        if elided {
+            cells.push(vec!["...".to_owned()]);
+        }
+
+        Block::new(align(&cells)).boxed()
+    }
+}
+
+fn pad(line: &str, width: usize) -> String {
+    format!("{}{}", line, " ".repeat(width - line.chars().count()))
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        line.to_owned()
+    } else {
+        let mut line: String = line.chars().take(width.saturating_sub(3)).collect();
+        line.push_str("...");
+
+        line
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+
+use vohtori::Value;
+
+use super::{raw, Formatter};
+
+/// Each element of a rank 2 array becomes a row. A rank 1 array is a single
+/// row and a scalar a single field, deeper nesting is written in plain form
+/// inside the field.
+pub struct Csv;
+
+impl Formatter for Csv {
+    fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        match value {
+            Value::Array(rows) if rows.iter().any(|r| matches!(r, Value::Array(_))) => {
+                for row in rows {
+                    write_row(&row, out)?;
+                }
+
+                Ok(())
+            }
+            _ => write_row(value, out),
+        }
+    }
+}
+
+fn write_row(row: &Value, out: &mut dyn Write) -> io::Result<()> {
+    match row {
+        Value::Array(fields) => {
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+
+                write_field(&field, out)?;
+            }
+        }
+        _ => write_field(row, out)?,
+    }
+
+    writeln!(out)
+}
+
+fn write_field(field: &Value, out: &mut dyn Write) -> io::Result<()> {
+    let field = raw(field);
+
+    if field.contains([',', '"', '\n', '\r']) {
+        write!(out, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        write!(out, "{}", field)
+    }
+}
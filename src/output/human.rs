@@ -0,0 +1,27 @@
+use std::io::{self, Write};
+
+use vohtori::Value;
+
+use super::{align, matrix, raw, Formatter};
+
+/// Writes matrices as right-aligned columns and strings without quotes.
+pub struct Human;
+
+impl Formatter for Human {
+    fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        let Some(rows) = matrix(value).filter(|rows| !rows.is_empty()) else {
+            return writeln!(out, "{}", raw(value));
+        };
+
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| row.into_array().iter().map(|v| raw(&v)).collect())
+            .collect();
+
+        for line in align(&cells) {
+            writeln!(out, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use vohtori::Value;
+
+use super::Formatter;
+
+pub struct Json;
+
+impl Formatter for Json {
+    fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        write_value(value, out)?;
+        writeln!(out)
+    }
+}
+
+fn write_value(value: &Value, out: &mut dyn Write) -> io::Result<()> {
+    match value {
+        Value::Array(array) => {
+            write!(out, "[")?;
+
+            for (i, v) in array.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+
+                write_value(&v, out)?;
+            }
+
+            write!(out, "]")
+        }
+        Value::Map(map) => {
+            write!(out, "{{")?;
+
+            // JSON only has string keys, other keys use their plain form.
+            for (i, (k, v)) in map.entries().iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+
+                match k {
+                    Value::String(k) => write_string(k, out)?,
+                    _ => write_string(&k.to_string(), out)?,
+                }
+
+                write!(out, ":")?;
+                write_value(v, out)?;
+            }
+
+            write!(out, "}}")
+        }
+        Value::Number(number) => write!(out, "{}", number.value),
+        Value::String(string) => write_string(string, out),
+        Value::Function(function) => write_string(&function.to_string(), out),
+    }
+}
+
+fn write_string(string: &str, out: &mut dyn Write) -> io::Result<()> {
+    write!(out, "\"")?;
+
+    for c in string.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+
+    write!(out, "\"")
+}
@@ -0,0 +1,31 @@
+use std::io::{self, Write};
+
+use vohtori::Value;
+
+use super::{raw, Formatter};
+
+/// Strings are written without quotes so the output can be piped into other
+/// line based tools.
+pub struct Lines;
+
+impl Formatter for Lines {
+    fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        match value {
+            Value::Array(array) => {
+                for v in array {
+                    writeln!(out, "{}", raw(&v))?;
+                }
+
+                Ok(())
+            }
+            Value::Map(map) => {
+                for (k, v) in map.entries() {
+                    writeln!(out, "{}: {}", raw(k), raw(v))?;
+                }
+
+                Ok(())
+            }
+            _ => writeln!(out, "{}", raw(value)),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+
+use vohtori::{Array, Value};
+
+mod boxed;
+mod csv;
+mod human;
+mod json;
+mod lines;
+mod plain;
+
+pub use boxed::Boxed;
+pub use csv::Csv;
+pub use human::Human;
+pub use json::Json;
+pub use lines::Lines;
+pub use plain::Plain;
+
+/// Writes one evaluated value, including the trailing newline.
+pub trait Formatter {
+    fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()>;
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Output {
+    /// Nested arrays on one line, as in `[1 [2 3]]`
+    #[default]
+    Plain,
+    /// Matrices as right-aligned columns, everything else as `plain`
+    Human,
+    /// Every array drawn inside a box, the default in interactive mode
+    Boxed,
+    /// One JSON document per line
+    Json,
+    /// Rows of comma separated fields, for rank 2 results
+    Csv,
+    /// One element of an array per line
+    Lines,
+}
+
+impl Output {
+    pub fn formatter(self) -> Box<dyn Formatter> {
+        match self {
+            Output::Plain => Box::new(Plain),
+            Output::Human => Box::new(Human),
+            Output::Boxed => Box::new(Boxed::default()),
+            Output::Json => Box::new(Json),
+            Output::Csv => Box::new(Csv),
+            Output::Lines => Box::new(Lines),
+        }
+    }
+}
+
+/// A rank 2 array whose elements are all scalars. Its rows are read one at a
+/// time rather than copied, and a flat one is known to be a matrix from its
+/// shape alone.
+fn matrix(value: &Value) -> Option<&Array> {
+    let Value::Array(rows) = value else {
+        return None;
+    };
+
+    if rows.shape().is_some_and(|shape| shape.len() == 2) {
+        return Some(rows);
+    }
+
+    rows.iter()
+        .all(|row| matches!(row, Value::Array(row) if row.iter().all(|v| is_scalar(&v))))
+        .then_some(rows)
+}
+
+/// Joins the cells of each row with spaces, right-aligning every column to
+/// its widest cell.
+fn align(cells: &[Vec<String>]) -> Vec<String> {
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:>width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+/// The plain form of a value, except that strings are not quoted.
+fn raw(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Map(_))
+}
@@ -0,0 +1,13 @@
+use std::io::{self, Write};
+
+use vohtori::Value;
+
+use super::Formatter;
+
+pub struct Plain;
+
+impl Formatter for Plain {
+    fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", value)
+    }
+}
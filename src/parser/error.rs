@@ -7,34 +7,46 @@ pub enum ParserError {
     InvalidTokenType(InvalidTokenTypeError),
     MissingToken(MissingTokenError),
     UnexpectedEndOfLine(UnexpectedEndOfLineError),
+    InvalidCharacter(char),
+    InvalidNumber(String),
 }
 
 impl Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ParserError::InvalidTokenType(err) => {
-                write!(f, "Expected one of [")?;
-                let len = err.expected.len();
-
-                for (i, tt) in err.expected.iter().enumerate() {
-                    write!(f, "{:?}", tt)?;
-
-                    if i < (len - 1) {
-                        write!(f, ", ")?;
-                    }
-                }
-
-                write!(f, "], found {:?}", err.found)?;
+                write!(f, "Expected one of ")?;
+                write_token_types(f, &err.expected)?;
+                write!(f, ", found {:?}", err.found)?;
+            }
+            ParserError::MissingToken(err) => {
+                write!(f, "Missing one of ")?;
+                write_token_types(f, &err.expected)?;
             }
-            ParserError::MissingToken(_) => todo!(),
             ParserError::UnexpectedEndOfLine(_) => {
                 write!(f, "Unexpected EOL")?;
             }
+            ParserError::InvalidCharacter(c) => write!(f, "Invalid character {:?}", c)?,
+            ParserError::InvalidNumber(number) => write!(f, "Number {} does not fit", number)?,
         }
         Ok(())
     }
 }
 
+fn write_token_types(f: &mut std::fmt::Formatter<'_>, types: &[TokenType]) -> std::fmt::Result {
+    write!(f, "[")?;
+
+    for (i, tt) in types.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+
+        write!(f, "{:?}", tt)?;
+    }
+
+    write!(f, "]")
+}
+
 #[derive(Debug)]
 pub struct InvalidTokenTypeError {
     pub expected: Vec<TokenType>,
@@ -6,46 +6,73 @@ use crate::{
     runtime::operation::Modifier,
 };
 
-mod error;
+pub mod error;
 mod tokenstream;
 pub use tokenstream::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Operator {
     pub name: String,
     pub modifiers: Modifier,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Identifier {
     pub name: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Number {
     pub value: isize,
 }
 
-#[derive(Debug)]
+/// Anonymous lambdas have no named parameters and only read their
+/// arguments through `$0`, `$1`, ...
+#[derive(Debug, Clone)]
 pub struct Lambda {
+    pub params: Vec<Identifier>,
     pub body: Box<Expr>,
 }
 
-#[derive(Debug)]
+/// `if condition then expr else expr`, only the taken branch is evaluated.
+#[derive(Debug, Clone)]
+pub struct Conditional {
+    pub condition: Box<Expr>,
+    pub then: Box<Expr>,
+    pub otherwise: Box<Expr>,
+}
+
+/// `fn name params...: body`
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: Identifier,
+    pub lambda: Lambda,
+}
+
+/// `$0` refers to the innermost lambda's arguments, every additional `$`
+/// moves one enclosing lambda outwards.
+#[derive(Debug, Clone)]
 pub struct Arg {
     pub index: usize,
+    pub depth: usize,
 }
 
-#[derive(Debug)]
+/// A parsed line, ready for `Runtime::eval_expr`.
+#[derive(Debug, Clone)]
 pub enum Expr {
     Binary(Operator, Box<Expr>, Box<Expr>),
     Unary(Operator, Box<Expr>),
     Function(Identifier),
     Variable(Identifier),
     Number(Number),
+    String(String),
     Array(Vec<Expr>),
+    Map(Vec<(Expr, Expr)>),
     Lambda(Lambda),
     Call(Box<Expr>, Box<Expr>),
+    Assign(Identifier, Box<Expr>),
+    Define(Definition),
+    Conditional(Conditional),
     Argument(Arg),
 }
 
@@ -54,12 +81,18 @@ pub struct Parser<T: BufRead> {
 }
 
 impl<T: BufRead> Parser<T> {
-    pub fn new(source: SourceCursor<T>) -> Self {
-        Self { source }
+    pub fn new(input: T) -> Self {
+        Self {
+            source: SourceCursor::new(input),
+        }
     }
 
     pub fn parse_expr(&mut self) -> Option<Result<Expr, ParserError>> {
-        let token_stream = TokenStream::new(self.source.tokenize_line()?);
+        let tokens = match self.source.tokenize_line()? {
+            Ok(tokens) => tokens,
+            Err(err) => return Some(Err(err)),
+        };
+        let token_stream = TokenStream::new(tokens);
         let mut line = Line::new(token_stream);
 
         Some(line.parse_expr())
@@ -75,15 +108,6 @@ impl Line {
         Self { token_stream }
     }
 
-    fn consume(&mut self, tt: TokenType) -> bool {
-        let matching = self.token_stream.peek().is_some_and(|t| t.token_type == tt);
-        if matching {
-            self.token_stream.next();
-        }
-
-        matching
-    }
-
     fn consume_with_content(&mut self, tt: TokenType, content: &str) -> bool {
         let matching = self
             .token_stream
@@ -100,7 +124,7 @@ impl Line {
         let lhs = self.parse_term()?;
 
         if self.expect(TokenType::Operator) {
-            let op = self.parse_operator();
+            let op = self.parse_operator()?;
             let rhs = self.parse_expr()?;
             return Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)));
         }
@@ -109,7 +133,11 @@ impl Line {
     }
 
     fn parse_term(&mut self) -> Result<Expr, ParserError> {
-        if self.expect(TokenType::Identifier) {
+        if self.consume_with_content(TokenType::Identifier, "fn") {
+            self.parse_definition()
+        } else if self.consume_with_content(TokenType::Identifier, "if") {
+            self.parse_conditional()
+        } else if self.expect(TokenType::Identifier) {
             let ident = Identifier {
                 name: self.token_stream.next().unwrap().content,
             };
@@ -118,13 +146,17 @@ impl Line {
                     Box::new(Expr::Function(ident)),
                     Box::new(self.parse_expr()?),
                 ))
+            } else if self.consume_with_content(TokenType::Operator, "=") {
+                Ok(Expr::Assign(ident, Box::new(self.parse_expr()?)))
             } else {
                 Ok(Expr::Variable(ident))
             }
         } else if self.expect(TokenType::Literal(LiteralType::Number)) {
             Ok(Expr::Number(Number {
-                value: self.token_stream.next().unwrap().content.parse().unwrap(),
+                value: self.parse_number()?,
             }))
+        } else if self.expect(TokenType::Literal(LiteralType::String)) {
+            Ok(Expr::String(self.token_stream.next().unwrap().content))
         } else if self.consume_with_content(TokenType::SyntaxToken, "(") {
             let expr = self.parse_expr();
             self.consume_with_content(TokenType::SyntaxToken, ")");
@@ -139,13 +171,36 @@ impl Line {
             self.consume_with_content(TokenType::SyntaxToken, "]");
 
             Ok(Expr::Array(array))
+        } else if self.consume_with_content(TokenType::SyntaxToken, "{") {
+            let mut map = vec![];
+
+            while !self.expect_with_content(TokenType::SyntaxToken, "}") {
+                let key = self.parse_key()?;
+
+                if !self.consume_with_content(TokenType::SyntaxToken, ":") {
+                    return Err(self.unexpected_token(vec![TokenType::SyntaxToken]));
+                }
+
+                map.push((key, self.parse_term()?));
+            }
+            self.consume_with_content(TokenType::SyntaxToken, "}");
+
+            Ok(Expr::Map(map))
         } else if self.expect(TokenType::Operator) {
-            let op = self.parse_operator();
+            let op = self.parse_operator()?;
             let expr = self.parse_expr()?;
 
             Ok(Expr::Unary(op, Box::new(expr)))
         } else if self.consume_with_content(TokenType::SyntaxToken, "$") {
-            if self.expect(TokenType::Identifier) {
+            if self.expect_with_content(TokenType::SyntaxToken, "$") {
+                let mut depth = 0;
+
+                while self.consume_with_content(TokenType::SyntaxToken, "$") {
+                    depth += 1;
+                }
+
+                self.parse_argument(depth)
+            } else if self.expect(TokenType::Identifier) {
                 let ident = self.parse_identifier()?;
 
                 if self.consume_with_content(TokenType::SyntaxToken, ":") {
@@ -164,47 +219,107 @@ impl Line {
 
                     Ok(Expr::Call(
                         Box::new(Expr::Lambda(Lambda {
+                            params: vec![],
                             body: Box::new(function),
                         })),
                         Box::new(expr),
                     ))
                 } else {
                     Ok(Expr::Lambda(Lambda {
+                        params: vec![],
                         body: Box::new(function),
                     }))
                 }
             } else if self.expect(TokenType::Literal(LiteralType::Number)) {
-                let num = self.token_stream.next().unwrap().content.parse().unwrap();
-                Ok(Expr::Argument(Arg { index: num }))
+                self.parse_argument(0)
             } else {
-                Err(ParserError::InvalidTokenType(InvalidTokenTypeError::new(
-                    vec![TokenType::SyntaxToken],
-                    self.token_stream.next().unwrap().token_type,
-                )))
+                Err(self.unexpected_token(vec![TokenType::SyntaxToken]))
             }
         } else {
             Err(ParserError::UnexpectedEndOfLine(UnexpectedEndOfLineError))
         }
     }
 
-    fn parse_factor(&mut self) -> Result<Expr, ParserError> {
+    fn parse_definition(&mut self) -> Result<Expr, ParserError> {
+        if !self.expect(TokenType::Identifier) {
+            return Err(self.unexpected_token(vec![TokenType::Identifier]));
+        }
+
+        let name = self.parse_identifier()?;
+        let mut params = vec![];
+
+        while !self.consume_with_content(TokenType::SyntaxToken, ":") {
+            if !self.expect(TokenType::Identifier) {
+                return Err(
+                    self.unexpected_token(vec![TokenType::Identifier, TokenType::SyntaxToken])
+                );
+            }
+
+            params.push(self.parse_identifier()?);
+        }
+
+        Ok(Expr::Define(Definition {
+            name,
+            lambda: Lambda {
+                params,
+                body: Box::new(self.parse_expr()?),
+            },
+        }))
+    }
+
+    fn parse_conditional(&mut self) -> Result<Expr, ParserError> {
+        let condition = self.parse_expr()?;
+
+        if !self.consume_with_content(TokenType::Identifier, "then") {
+            return Err(self.unexpected_token(vec![TokenType::Identifier]));
+        }
+
+        let then = self.parse_expr()?;
+
+        if !self.consume_with_content(TokenType::Identifier, "else") {
+            return Err(self.unexpected_token(vec![TokenType::Identifier]));
+        }
+
+        Ok(Expr::Conditional(Conditional {
+            condition: Box::new(condition),
+            then: Box::new(then),
+            otherwise: Box::new(self.parse_expr()?),
+        }))
+    }
+
+    fn parse_argument(&mut self, depth: usize) -> Result<Expr, ParserError> {
+        if self.expect(TokenType::Literal(LiteralType::Number)) {
+            let index = self.parse_number()?;
+            Ok(Expr::Argument(Arg { index, depth }))
+        } else {
+            Err(self.unexpected_token(vec![TokenType::Literal(LiteralType::Number)]))
+        }
+    }
+
+    /// Map keys are terms, except that a bare identifier is always a variable
+    /// since the `:` following it separates the key from the value.
+    fn parse_key(&mut self) -> Result<Expr, ParserError> {
         if self.expect(TokenType::Identifier) {
-            Ok(Expr::Variable(Identifier {
-                name: self.token_stream.next().unwrap().content,
-            }))
-        } else if self.expect(TokenType::Literal(LiteralType::Number)) {
-            Ok(Expr::Number(Number {
-                value: self.token_stream.next().unwrap().content.parse().unwrap(),
-            }))
+            Ok(Expr::Variable(self.parse_identifier()?))
         } else {
-            Err(InvalidTokenTypeError::new(
-                vec![
-                    TokenType::Identifier,
-                    TokenType::Literal(LiteralType::Number),
-                ],
-                self.token_stream.next().unwrap().token_type,
-            )
-            .into())
+            self.parse_term()
+        }
+    }
+
+    /// The next token as a number, which has to fit the type it is parsed
+    /// into.
+    fn parse_number<N: std::str::FromStr>(&mut self) -> Result<N, ParserError> {
+        let content = self.token_stream.next().unwrap().content;
+
+        content
+            .parse()
+            .map_err(|_| ParserError::InvalidNumber(content))
+    }
+
+    fn unexpected_token(&mut self, expected: Vec<TokenType>) -> ParserError {
+        match self.token_stream.next() {
+            Some(token) => InvalidTokenTypeError::new(expected, token.token_type).into(),
+            None => ParserError::UnexpectedEndOfLine(UnexpectedEndOfLineError),
         }
     }
 
@@ -221,22 +336,26 @@ impl Line {
         }
     }
 
-    fn parse_operator(&mut self) -> Operator {
+    fn parse_operator(&mut self) -> Result<Operator, ParserError> {
         if self.expect(TokenType::Operator) {
             let operator = self.token_stream.next().unwrap().content;
             let mut modifiers = Modifier::default();
 
             if self.consume_with_content(TokenType::SyntaxToken, ":") {
+                if !self.expect(TokenType::Operator) {
+                    return Err(self.unexpected_token(vec![TokenType::Operator]));
+                }
+
                 modifiers |= parse_modifier(&self.token_stream.next().unwrap().content);
             }
 
-            return Operator {
+            return Ok(Operator {
                 name: operator,
                 modifiers,
-            };
+            });
         }
 
-        todo!()
+        Err(self.unexpected_token(vec![TokenType::Operator]))
     }
 
     fn expect(&self, tt: TokenType) -> bool {
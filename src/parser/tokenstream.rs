@@ -1,4 +1,4 @@
-use crate::lexer::{SourceCursor, Token};
+use crate::lexer::Token;
 
 #[derive(Clone)]
 pub struct TokenStream {
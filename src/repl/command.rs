@@ -0,0 +1,118 @@
+use vohtori::Value;
+
+/// Lines starting with a colon are commands to the REPL itself and never
+/// reach the parser.
+pub enum Command<'a> {
+    Vars,
+    Ast(&'a str),
+    Time(&'a str),
+    Load(&'a str),
+    Save(&'a str),
+    LoadWorkspace(&'a str),
+    Reset,
+    Help(Option<&'a str>),
+    Quit,
+}
+
+/// Every command with its usage and description, as listed by `:help`.
+pub const COMMANDS: &[(&str, &str, &str)] = &[
+    (":vars", ":vars", "List the variables and their shapes"),
+    (
+        ":ast",
+        ":ast expr",
+        "Show the parsed expression without evaluating it",
+    ),
+    (
+        ":time",
+        ":time expr",
+        "Evaluate an expression and show how long it took",
+    ),
+    (":load", ":load file", "Evaluate every line of a script"),
+    (
+        ":save",
+        ":save file",
+        "Save the variables to a workspace file",
+    ),
+    (
+        ":load-workspace",
+        ":load-workspace file",
+        "Restore the variables saved in a workspace file",
+    ),
+    (":reset", ":reset", "Forget every variable and defined function"),
+    (
+        ":help",
+        ":help [name]",
+        "List the commands and builtins, or describe one",
+    ),
+    (":quit", ":quit", "Leave the REPL"),
+];
+
+impl<'a> Command<'a> {
+    /// Parses a line starting with a colon.
+    pub fn parse(line: &'a str) -> Result<Self, String> {
+        let (name, arg) = match line.trim().split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (line.trim(), None),
+        };
+
+        let required = |arg: Option<&'a str>, what: &str| {
+            arg.ok_or_else(|| format!("{} expects {}", name, what))
+        };
+
+        Ok(match name {
+            ":vars" => Command::Vars,
+            ":ast" => Command::Ast(required(arg, "an expression")?),
+            ":time" => Command::Time(required(arg, "an expression")?),
+            ":load" => Command::Load(required(arg, "a file")?),
+            ":save" => Command::Save(required(arg, "a file")?),
+            ":load-workspace" => Command::LoadWorkspace(required(arg, "a file")?),
+            ":reset" => Command::Reset,
+            ":help" => Command::Help(arg),
+            ":quit" | ":q" => Command::Quit,
+            _ => return Err(format!("Unknown command {}, see :help", name)),
+        })
+    }
+}
+
+/// Describes a value by its type and, for arrays, its dimensions.
+pub fn shape(value: &Value) -> String {
+    match value {
+        Value::Number(_) => "number".to_owned(),
+        Value::String(_) => "string".to_owned(),
+        Value::Function(_) => "function".to_owned(),
+        Value::Map(map) => format!("map of {}", map.len()),
+        Value::Array(arr) => match dimensions(value) {
+            Some(dimensions) => {
+                let dimensions: Vec<String> = dimensions.iter().map(|d| d.to_string()).collect();
+                format!("array {}", dimensions.join("×"))
+            }
+            None => format!("ragged array of {}", arr.len()),
+        },
+    }
+}
+
+/// The length along each axis of a rectangular array, `None` if the rows
+/// differ in shape.
+fn dimensions(value: &Value) -> Option<Vec<usize>> {
+    let Value::Array(arr) = value else {
+        return Some(vec![]);
+    };
+
+    if let Some(shape) = arr.shape() {
+        return Some(shape.to_vec());
+    }
+
+    let mut elements = arr.iter().map(|v| dimensions(&v));
+    let inner = match elements.next() {
+        Some(first) => first?,
+        None => vec![],
+    };
+
+    for element in elements {
+        if element? != inner {
+            return None;
+        }
+    }
+
+    Some([vec![arr.len()], inner].concat())
+}
@@ -0,0 +1,103 @@
+use vohtori::{builtin, Function, Runtime};
+
+use super::command::{self, COMMANDS};
+
+/// A name the editor can complete, with what is shown once it is the only
+/// match.
+pub struct Candidate {
+    pub name: String,
+    pub signature: String,
+}
+
+/// Collects the variables, functions and commands known before an entry is
+/// read.
+pub fn candidates(runtime: &Runtime) -> Vec<Candidate> {
+    let variables = runtime.variables().map(|(name, value)| Candidate {
+        name: name.to_owned(),
+        signature: format!(
+            "{}: {}",
+            name,
+            value.map_or("not read yet".to_owned(), command::shape)
+        ),
+    });
+    let functions = runtime.functions().map(|(name, function)| Candidate {
+        name: name.to_owned(),
+        signature: signature(name, function),
+    });
+    let commands = COMMANDS.iter().map(|(name, usage, _)| Candidate {
+        name: (*name).to_owned(),
+        signature: (*usage).to_owned(),
+    });
+
+    variables.chain(functions).chain(commands).collect()
+}
+
+/// Builtins show their documented signature, other functions how many
+/// arguments they take.
+fn signature(name: &str, function: &Function) -> String {
+    let arity = match function {
+        Function::Builtin(builtin_name) => match builtin(builtin_name) {
+            Some(builtin) => return builtin.signature.to_owned(),
+            None => None,
+        },
+        Function::Native(native) => Some(native.arity),
+        Function::Lambda(closure) => Some(closure.chunk.params).filter(|&params| params > 0),
+    };
+
+    match arity {
+        Some(1) => format!("{}: value", name),
+        Some(arity) => format!("{}: [{}]", name, vec!["value"; arity].join(" ")),
+        None => format!("{}: arguments", name),
+    }
+}
+
+/// Finds the word ending at the end of `line` and the candidates it is a
+/// prefix of, sorted by name. Commands are only offered at the start of a
+/// line.
+pub fn complete<'a>(candidates: &'a [Candidate], line: &[char]) -> (usize, Vec<&'a Candidate>) {
+    let mut start = line.len();
+
+    while start > 0 && (line[start - 1].is_alphabetic() || line[start - 1] == '_') {
+        start -= 1;
+    }
+
+    // A command is the first word of a line and may contain dashes.
+    let first = line.iter().take_while(|c| c.is_whitespace()).count();
+    if line.get(first) == Some(&':') && !line[first..].iter().any(|c| c.is_whitespace()) {
+        start = first;
+    }
+
+    let word: String = line[start..].iter().collect();
+    if word.is_empty() {
+        return (start, vec![]);
+    }
+
+    let mut matches: Vec<_> = candidates
+        .iter()
+        .filter(|candidate| candidate.name.starts_with(&word))
+        .collect();
+    matches.sort_by(|a, b| a.name.cmp(&b.name));
+    matches.dedup_by(|a, b| a.name == b.name);
+
+    (start, matches)
+}
+
+/// The longest prefix every match shares.
+pub fn common_prefix(matches: &[&Candidate]) -> String {
+    let Some((first, rest)) = matches.split_first() else {
+        return String::new();
+    };
+
+    let mut prefix = first.name.as_str();
+    for candidate in rest {
+        let length = prefix
+            .char_indices()
+            .zip(candidate.name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(candidate.name.len()), |((i, _), _)| i);
+
+        prefix = &prefix[..length];
+    }
+
+    prefix.to_owned()
+}
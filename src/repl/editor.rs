@@ -0,0 +1,254 @@
+use std::io::{self, BufRead, Write};
+
+use super::{
+    completion::{self, Candidate},
+    history::History,
+    terminal::{self, Key, RawInput, RawMode},
+};
+
+pub const PROMPT: &str = "  > ";
+pub const CONTINUATION_PROMPT: &str = "  . ";
+
+pub enum Input {
+    Line(String),
+    Interrupt,
+    Eof,
+}
+
+/// Reads REPL entries, with line editing and history when attached to a
+/// terminal and plain line reading otherwise. Entries read from a pipe or
+/// file are not recorded, so running a script leaves the history alone.
+pub struct Editor {
+    history: History,
+    tty: bool,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let tty = terminal::is_tty();
+        let history = if tty {
+            History::load()
+        } else {
+            History::empty()
+        };
+
+        Self { history, tty }
+    }
+
+    /// Reads one entry, asking for more lines while brackets are left open.
+    /// The lines of an entry are joined with spaces since the parser handles
+    /// one line at a time. Tab completes from `candidates`.
+    pub fn read_entry(&mut self, candidates: &[Candidate]) -> io::Result<Option<String>> {
+        let mut entry = String::new();
+
+        loop {
+            let prompt = if entry.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+
+            match self.read_line(prompt, candidates)? {
+                Input::Line(line) => {
+                    if !entry.is_empty() {
+                        entry.push(' ');
+                    }
+                    entry.push_str(&line);
+
+                    if open_brackets(&entry) <= 0 {
+                        if self.tty {
+                            self.history.push(&entry);
+                        }
+
+                        return Ok(Some(entry));
+                    }
+                }
+                Input::Interrupt => entry.clear(),
+                Input::Eof if entry.is_empty() => return Ok(None),
+                Input::Eof => return Ok(Some(entry)),
+            }
+        }
+    }
+
+    fn read_line(&mut self, prompt: &str, candidates: &[Candidate]) -> io::Result<Input> {
+        if !self.tty {
+            let mut line = String::new();
+
+            return Ok(match io::stdin().lock().read_line(&mut line)? {
+                0 => Input::Eof,
+                _ => Input::Line(line.trim_end_matches(['\n', '\r']).to_owned()),
+            });
+        }
+
+        let _raw = RawMode::enable()?;
+        let mut stdout = io::stdout().lock();
+
+        let mut line = LineBuffer::default();
+        // Index into the history, equal to its length while editing a new line.
+        let mut position = self.history.entries().len();
+        let mut draft = vec![];
+
+        line.refresh(&mut stdout, prompt)?;
+
+        loop {
+            let Some(key) = terminal::read_key(&mut RawInput)? else {
+                return Ok(Input::Eof);
+            };
+
+            match key {
+                Key::Enter => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(Input::Line(line.chars.iter().collect()));
+                }
+                Key::Ctrl('c') => {
+                    write!(stdout, "^C\r\n")?;
+                    return Ok(Input::Interrupt);
+                }
+                Key::Ctrl('d') if line.chars.is_empty() => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(Input::Eof);
+                }
+                Key::Ctrl('d') | Key::Delete => line.delete(),
+                Key::Backspace | Key::Ctrl('h') => line.backspace(),
+                Key::Left | Key::Ctrl('b') => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => {
+                    line.cursor = (line.cursor + 1).min(line.chars.len())
+                }
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.chars.len(),
+                Key::Ctrl('u') => {
+                    line.chars.drain(..line.cursor);
+                    line.cursor = 0;
+                }
+                Key::Ctrl('k') => line.chars.truncate(line.cursor),
+                Key::Ctrl('w') => line.delete_word(),
+                Key::Up if position > 0 => {
+                    if position == self.history.entries().len() {
+                        draft = line.chars.clone();
+                    }
+
+                    position -= 1;
+                    line.set(self.history.entries()[position].chars().collect());
+                }
+                Key::Down if position < self.history.entries().len() => {
+                    position += 1;
+
+                    match self.history.entries().get(position) {
+                        Some(entry) => line.set(entry.chars().collect()),
+                        None => line.set(std::mem::take(&mut draft)),
+                    }
+                }
+                Key::Tab => {
+                    let (start, matches) =
+                        completion::complete(candidates, &line.chars[..line.cursor]);
+
+                    match matches.as_slice() {
+                        [] => continue,
+                        [candidate] => {
+                            line.replace_word(start, &candidate.name);
+                            write!(stdout, "\r\n{}\x1b[K\r\n", candidate.signature)?;
+                        }
+                        _ => {
+                            let prefix = completion::common_prefix(&matches);
+
+                            if prefix.chars().count() > line.cursor - start {
+                                line.replace_word(start, &prefix);
+                            } else {
+                                let names: Vec<_> =
+                                    matches.iter().map(|c| c.name.as_str()).collect();
+                                write!(stdout, "\r\n{}\x1b[K\r\n", names.join("  "))?;
+                            }
+                        }
+                    }
+                }
+                Key::Char(c) => line.insert(c),
+                _ => continue,
+            }
+
+            line.refresh(&mut stdout, prompt)?;
+        }
+    }
+}
+
+#[derive(Default)]
+struct LineBuffer {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineBuffer {
+    fn set(&mut self, chars: Vec<char>) {
+        self.cursor = chars.len();
+        self.chars = chars;
+    }
+
+    fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    /// Replaces the text between `start` and the cursor with `word`.
+    fn replace_word(&mut self, start: usize, word: &str) {
+        self.chars.splice(start..self.cursor, word.chars());
+        self.cursor = start + word.chars().count();
+    }
+
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+
+        while start > 0 && self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Redraws the whole line and places the terminal cursor.
+    fn refresh(&self, out: &mut impl Write, prompt: &str) -> io::Result<()> {
+        let line: String = self.chars.iter().collect();
+        write!(out, "\r{}{}\x1b[K", prompt, line)?;
+
+        let column = prompt.chars().count() + self.cursor;
+        write!(out, "\r")?;
+
+        if column > 0 {
+            write!(out, "\x1b[{}C", column)?;
+        }
+
+        out.flush()
+    }
+}
+
+/// Number of brackets left open, ignoring those inside string literals.
+pub fn open_brackets(source: &str) -> isize {
+    let mut depth = 0;
+    let mut string = false;
+
+    for c in source.chars() {
+        match c {
+            '"' => string = !string,
+            '(' | '[' | '{' if !string => depth += 1,
+            ')' | ']' | '}' if !string => depth -= 1,
+            _ => (),
+        }
+    }
+
+    depth
+}
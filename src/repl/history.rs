@@ -0,0 +1,90 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+const MAX_ENTRIES: usize = 1000;
+
+/// Previously entered lines, persisted in `~/.vohtori_history`.
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// A history that is neither read nor saved.
+    pub fn empty() -> Self {
+        Self {
+            entries: vec![],
+            path: None,
+        }
+    }
+
+    pub fn load() -> Self {
+        let path =
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".vohtori_history"));
+
+        let mut entries: Vec<String> = path
+            .as_ref()
+            .and_then(|path| File::open(path).ok())
+            .map(|file| BufReader::new(file).lines().map_while(Result::ok).collect())
+            .unwrap_or_default();
+
+        let truncate = entries.len() > MAX_ENTRIES;
+
+        if truncate {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+
+        let history = Self { entries, path };
+
+        // Dropping old entries from the file is best effort, a failure only
+        // means it keeps growing.
+        if truncate {
+            let _ = history.rewrite();
+        }
+
+        history
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, entry: &str) {
+        if entry.trim().is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return;
+        }
+
+        self.entries.push(entry.to_owned());
+
+        if let Err(e) = self.append(entry) {
+            eprintln!("Failed to save history: {}", e);
+            self.path = None;
+        }
+    }
+
+    fn rewrite(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut file = File::create(path)?;
+
+        for entry in &self.entries {
+            writeln!(file, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+
+    fn append(&self, entry: &str) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", entry)
+    }
+}
@@ -0,0 +1,201 @@
+pub mod command;
+pub mod completion;
+pub mod editor;
+mod history;
+pub mod terminal;
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    ops::ControlFlow,
+    path::Path,
+    time::Instant,
+};
+
+use vohtori::{Parser, Runtime, RuntimeError};
+
+use crate::{output::Formatter, workspace};
+
+use self::{
+    command::{Command, COMMANDS},
+    editor::Editor,
+};
+
+pub fn run(runtime: &mut Runtime, formatter: &dyn Formatter, show_parser: bool) {
+    let mut editor = Editor::new();
+
+    // Ctrl-C stops the running evaluation rather than the whole session.
+    if let Err(e) = terminal::forward_sigint(runtime.interrupt_flag()) {
+        eprintln!("Failed to handle interrupts: {}", e);
+    }
+    let mut session = Session {
+        runtime,
+        formatter,
+        show_parser,
+    };
+
+    loop {
+        let candidates = completion::candidates(session.runtime);
+
+        let entry = match editor.read_entry(&candidates) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("Failed to read input: {}", e);
+                break;
+            }
+        };
+
+        if entry.trim().is_empty() {
+            continue;
+        }
+
+        if entry.trim_start().starts_with(':') {
+            match Command::parse(&entry) {
+                Ok(command) => {
+                    if session.command(command).is_break() {
+                        break;
+                    }
+                }
+                Err(e) => println!("{}", e),
+            }
+        } else {
+            session.eval_source(entry.as_bytes(), false);
+        }
+    }
+}
+
+struct Session<'a> {
+    runtime: &'a mut Runtime,
+    formatter: &'a dyn Formatter,
+    show_parser: bool,
+}
+
+impl Session<'_> {
+    fn command(&mut self, command: Command) -> ControlFlow<()> {
+        match command {
+            Command::Vars => {
+                let mut variables: Vec<_> = self.runtime.variables().collect();
+                variables.sort_by_key(|&(name, _)| name);
+
+                let width = variables.iter().map(|(name, _)| name.len()).max();
+                for (name, value) in variables {
+                    let shape = match value {
+                        Some(value) => command::shape(value),
+                        None => "not read yet".to_owned(),
+                    };
+
+                    println!("  {:width$}  {}", name, shape, width = width.unwrap_or(0));
+                }
+            }
+            Command::Ast(source) => {
+                let mut parser = Parser::new(source.as_bytes());
+
+                while let Some(expr) = parser.parse_expr() {
+                    match expr {
+                        Ok(expr) => println!("{:#?}", expr),
+                        Err(e) => println!("{:?}", e),
+                    }
+                }
+            }
+            Command::Time(source) => self.eval_source(source.as_bytes(), true),
+            Command::Load(path) => match File::open(path) {
+                Ok(file) => self.eval_source(BufReader::new(file), false),
+                Err(e) => println!("Failed to open {}: {}", path, e),
+            },
+            Command::Save(path) => match workspace::save_file(self.runtime, Path::new(path)) {
+                Ok(skipped) => {
+                    for name in skipped {
+                        println!("Skipped {}, only builtin functions can be saved", name);
+                    }
+                }
+                Err(e) => println!("Failed to save {}: {}", path, e),
+            },
+            Command::LoadWorkspace(path) => {
+                match workspace::load_file(self.runtime, Path::new(path)) {
+                    Ok(count) => println!("Restored {} variables", count),
+                    Err(e) => println!("Failed to load {}: {}", path, e),
+                }
+            }
+            Command::Reset => self.runtime.reset(),
+            Command::Help(None) => {
+                let width = COMMANDS.iter().map(|(_, usage, _)| usage.len()).max();
+                let width = width.unwrap_or(0) + 2;
+
+                for (_, usage, description) in COMMANDS {
+                    println!("  {:width$}{}", usage, description, width = width);
+                }
+
+                let builtins: Vec<_> = vohtori::BUILTINS.iter().map(|b| b.name).collect();
+                println!();
+                println!("  Builtins: {}", builtins.join(" "));
+            }
+            Command::Help(Some(name)) => {
+                if let Some(builtin) = vohtori::builtin(name) {
+                    println!("  {}", builtin.signature);
+                    println!("  {}", builtin.doc);
+                } else if let Some((_, usage, description)) =
+                    COMMANDS.iter().find(|(command, ..)| *command == name)
+                {
+                    println!("  {}", usage);
+                    println!("  {}", description);
+                } else {
+                    println!("No builtin or command named {}", name);
+                }
+            }
+            Command::Quit => return ControlFlow::Break(()),
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Evaluates every expression in `source`, printing each result. The
+    /// whole source shares one budget of the limits, and an interrupt stops
+    /// it.
+    fn eval_source(&mut self, source: impl BufRead, timed: bool) {
+        let mut parser = Parser::new(source);
+        self.runtime.start();
+
+        while let Some(expr) = parser.parse_expr() {
+            if self.show_parser {
+                eprintln!("PARSER OUTPUT:");
+                eprintln!("{:#?}", expr);
+                eprintln!();
+            }
+
+            let expr = match expr {
+                Ok(expr) => expr,
+                Err(e) => {
+                    println!("{:?}", e);
+                    continue;
+                }
+            };
+
+            let start = Instant::now();
+            let result = self.runtime.eval_expr(&expr);
+            let elapsed = start.elapsed();
+
+            match result {
+                Ok(value) => {
+                    let mut out = vec![];
+                    self.formatter.write(&value, &mut out).unwrap();
+
+                    // Continuation lines are indented to line up with the
+                    // first one.
+                    for (i, line) in String::from_utf8_lossy(&out).lines().enumerate() {
+                        println!("{}{}", if i == 0 { "  = " } else { "    " }, line);
+                    }
+                }
+                Err(RuntimeError::Interrupted) => {
+                    println!("{}", RuntimeError::Interrupted);
+                    return;
+                }
+                Err(e) => println!("{}", e),
+            }
+
+            if timed {
+                println!("  ({:?})", elapsed);
+            }
+        }
+    }
+}
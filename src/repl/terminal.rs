@@ -0,0 +1,234 @@
+use std::{
+    io::{self, Read},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicPtr, Ordering},
+        Arc,
+    },
+};
+
+/// How long to wait for the rest of an escape sequence before taking ESC as
+/// a key of its own.
+const ESCAPE_TIMEOUT_MS: libc::c_int = 50;
+
+/// Puts the terminal on standard input into raw mode for as long as it is
+/// alive, so keys arrive one at a time without echo or line buffering.
+pub struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    pub fn enable() -> io::Result<Self> {
+        let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let original = termios;
+
+        termios.c_iflag &= !(libc::ICRNL | libc::IXON);
+        termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.original) };
+    }
+}
+
+/// The flag SIGINT sets, leaked so the handler can always reach it.
+static INTERRUPT: AtomicPtr<AtomicBool> = AtomicPtr::new(ptr::null_mut());
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    let flag = INTERRUPT.load(Ordering::Relaxed);
+
+    if !flag.is_null() {
+        unsafe { (*flag).store(true, Ordering::Relaxed) };
+    }
+}
+
+/// Makes SIGINT set `flag` instead of terminating the process.
+pub fn forward_sigint(flag: Arc<AtomicBool>) -> io::Result<()> {
+    INTERRUPT.store(Arc::into_raw(flag).cast_mut(), Ordering::Relaxed);
+
+    let mut action = unsafe { std::mem::zeroed::<libc::sigaction>() };
+    action.sa_sigaction = on_sigint as *const () as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESTART;
+
+    if unsafe { libc::sigaction(libc::SIGINT, &action, ptr::null_mut()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Key presses that can tell whether another byte is about to follow.
+pub trait Keys: Read {
+    /// Whether a byte can be read within `ESCAPE_TIMEOUT_MS`.
+    fn pending(&mut self) -> bool;
+}
+
+/// Standard input read straight from its file descriptor. Unlike `io::Stdin`
+/// it buffers nothing, so polling the descriptor sees every unread byte.
+pub struct RawInput;
+
+impl Read for RawInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+
+        match read {
+            -1 => Err(io::Error::last_os_error()),
+            read => Ok(read as usize),
+        }
+    }
+}
+
+impl Keys for RawInput {
+    fn pending(&mut self) -> bool {
+        let mut fd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        unsafe { libc::poll(&mut fd, 1, ESCAPE_TIMEOUT_MS) > 0 }
+    }
+}
+
+impl Keys for &[u8] {
+    fn pending(&mut self) -> bool {
+        !self.is_empty()
+    }
+}
+
+pub fn is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+/// The width of the terminal on standard output, if it is one that knows
+/// its size.
+pub fn columns() -> Option<usize> {
+    let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0 {
+        return None;
+    }
+
+    (size.ws_col > 0).then_some(size.ws_col.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Unknown,
+}
+
+/// Reads one key press, decoding UTF-8 characters and the common ANSI escape
+/// sequences. Returns `None` at end of input.
+pub fn read_key(input: &mut impl Keys) -> io::Result<Option<Key>> {
+    let Some(byte) = read_byte(input)? else {
+        return Ok(None);
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => read_escape(input)?,
+        0x01..=0x1a => Key::Ctrl((b'a' + byte - 1) as char),
+        0x00..=0x1f => Key::Unknown,
+        _ => {
+            let len = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+
+            let mut bytes = vec![byte];
+
+            for _ in 1..len {
+                match read_byte(input)? {
+                    Some(byte) => bytes.push(byte),
+                    None => break,
+                }
+            }
+
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            }
+        }
+    };
+
+    Ok(Some(key))
+}
+
+/// A bare ESC is not followed by anything, so it is not waited on.
+fn read_escape(input: &mut impl Keys) -> io::Result<Key> {
+    if !input.pending() {
+        return Ok(Key::Unknown);
+    }
+
+    let Some(first) = read_byte(input)? else {
+        return Ok(Key::Unknown);
+    };
+
+    if first != b'[' && first != b'O' {
+        return Ok(Key::Unknown);
+    }
+
+    let mut params = vec![];
+
+    loop {
+        match read_byte(input)? {
+            Some(byte @ b'0'..=b'9') | Some(byte @ b';') => params.push(byte),
+            Some(byte) => {
+                return Ok(match (byte, params.as_slice()) {
+                    (b'A', _) => Key::Up,
+                    (b'B', _) => Key::Down,
+                    (b'C', _) => Key::Right,
+                    (b'D', _) => Key::Left,
+                    (b'H', _) | (b'~', b"1") | (b'~', b"7") => Key::Home,
+                    (b'F', _) | (b'~', b"4") | (b'~', b"8") => Key::End,
+                    (b'~', b"3") => Key::Delete,
+                    _ => Key::Unknown,
+                })
+            }
+            None => return Ok(Key::Unknown),
+        }
+    }
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
@@ -0,0 +1,592 @@
+//! Array storage. Numbers in a regular shape are kept in one flat buffer
+//! together with their shape, ranges only as their first element and step,
+//! and anything else as a vector of values.
+
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::parser::Number;
+
+use super::{error::RuntimeError, Value};
+
+/// Elements are shared between copies of an array, so reading a variable or
+/// passing an array around never copies them. They are only copied when an
+/// array that is still shared is modified.
+///
+/// Arrays of numbers, and arrays of such arrays that all have the same shape,
+/// are stored flat so that arithmetic can loop over a plain buffer. Ranges
+/// are not stored at all until something needs their elements in memory, so
+/// reducing a large range, or adding, subtracting or multiplying it by a
+/// number, does not allocate. Any other operator stores its result. Ragged
+/// arrays and arrays of other values keep every element as a value. Which
+/// one is used never changes how an array compares or prints.
+#[derive(Clone, Debug)]
+pub struct Array {
+    storage: Storage,
+}
+
+#[derive(Clone, Debug)]
+enum Storage {
+    Nested(Rc<Vec<Value>>),
+    Flat(Flat),
+    /// `start`, `start + step`, ... with the length as a shape of rank 1.
+    Progression {
+        start: isize,
+        step: isize,
+        shape: [usize; 1],
+    },
+}
+
+/// The numbers from `offset` in `data` laid out in `shape`, which is never
+/// empty. Rows are views into the buffer of the array they are taken from.
+#[derive(Clone, Debug)]
+struct Flat {
+    shape: Vec<usize>,
+    data: Rc<Vec<isize>>,
+    offset: usize,
+}
+
+impl Flat {
+    fn slice(&self) -> &[isize] {
+        let len = self.shape.iter().product::<usize>();
+
+        &self.data[self.offset..self.offset + len]
+    }
+
+    /// The element at `index` along the first axis.
+    fn get(&self, index: usize) -> Option<Value> {
+        let (&len, inner) = self.shape.split_first()?;
+
+        if index >= len {
+            return None;
+        }
+
+        if inner.is_empty() {
+            return Some(Value::Number(Number {
+                value: self.data[self.offset + index],
+            }));
+        }
+
+        let row = Flat {
+            shape: inner.to_vec(),
+            data: self.data.clone(),
+            offset: self.offset + index * inner.iter().product::<usize>(),
+        };
+
+        Some(Value::Array(Array {
+            storage: Storage::Flat(row),
+        }))
+    }
+}
+
+impl Array {
+    /// Stores `value` flat if its elements allow it, see `Array`.
+    pub fn new(value: Vec<Value>) -> Self {
+        match pack(&value) {
+            Some(flat) => Self {
+                storage: Storage::Flat(flat),
+            },
+            None => Self::nested(value),
+        }
+    }
+
+    /// Keeps every element of `value` as a value, even if it could be stored
+    /// flat.
+    pub fn nested(value: Vec<Value>) -> Self {
+        Self {
+            storage: Storage::Nested(Rc::new(value)),
+        }
+    }
+
+    /// The numbers in `data` laid out in `shape`, whose lengths must
+    /// multiply to the length of `data`.
+    pub fn flat(shape: Vec<usize>, data: Vec<isize>) -> Self {
+        assert!(
+            !shape.is_empty() && shape.iter().product::<usize>() == data.len(),
+            "shape {:?} does not hold {} numbers",
+            shape,
+            data.len()
+        );
+
+        Self {
+            storage: Storage::Flat(Flat {
+                shape,
+                data: Rc::new(data),
+                offset: 0,
+            }),
+        }
+    }
+
+    /// `start..=end` without storing its elements, empty if `end` is less
+    /// than `start`.
+    pub fn range(start: isize, end: isize) -> Self {
+        let len = (end as i128 - start as i128 + 1).max(0);
+
+        Self::progression(start, 1, usize::try_from(len).unwrap_or(usize::MAX))
+    }
+
+    /// `len` numbers from `start` apart by `step`, without storing them. The
+    /// last one must not overflow.
+    pub fn progression(start: isize, step: isize, len: usize) -> Self {
+        Self {
+            storage: Storage::Progression {
+                start,
+                step,
+                shape: [len],
+            },
+        }
+    }
+
+    /// The first element and step of an array created as a progression.
+    pub fn as_progression(&self) -> Option<(isize, isize)> {
+        match self.storage {
+            Storage::Progression { start, step, .. } => Some((start, step)),
+            _ => None,
+        }
+    }
+
+    /// The length along each axis of a flat array or progression.
+    pub fn shape(&self) -> Option<&[usize]> {
+        match &self.storage {
+            Storage::Flat(flat) => Some(&flat.shape),
+            Storage::Progression { shape, .. } => Some(shape),
+            Storage::Nested(_) => None,
+        }
+    }
+
+    /// The numbers of a flat array or progression in row-major order.
+    pub fn numbers(&self) -> Option<Numbers<'_>> {
+        let inner = match self.storage {
+            Storage::Flat(ref flat) => NumbersInner::Flat(flat.slice().iter()),
+            Storage::Progression {
+                start,
+                step,
+                shape: [len],
+            } => NumbersInner::Progression {
+                next: start,
+                step,
+                remaining: len,
+            },
+            Storage::Nested(_) => return None,
+        };
+
+        Some(Numbers { inner })
+    }
+
+    /// The elements of an array that is stored as values.
+    pub fn elements(&self) -> Option<&[Value]> {
+        match &self.storage {
+            Storage::Nested(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Nested(value) => value.len(),
+            Storage::Flat(flat) => flat.shape[0],
+            Storage::Progression { shape: [len], .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<Value> {
+        match &self.storage {
+            Storage::Nested(value) => value.get(index).cloned(),
+            Storage::Flat(flat) => flat.get(index),
+            Storage::Progression {
+                start,
+                step,
+                shape: [len],
+            } => (index < *len).then(|| {
+                // Wrapping gives the right result whenever the element
+                // itself fits.
+                Value::Number(Number {
+                    value: start.wrapping_add(step.wrapping_mul(index as isize)),
+                })
+            }),
+        }
+    }
+
+    /// The first `n` elements, or all of them if there are fewer. Flat
+    /// arrays and progressions share their storage with the result.
+    pub fn take(&self, n: usize) -> Self {
+        let n = n.min(self.len());
+
+        match &self.storage {
+            Storage::Nested(value) => Self::new(value[..n].to_vec()),
+            Storage::Flat(flat) => {
+                let mut flat = flat.clone();
+                flat.shape[0] = n;
+
+                Self {
+                    storage: Storage::Flat(flat),
+                }
+            }
+            Storage::Progression { start, step, .. } => Self::progression(*start, *step, n),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            array: self,
+            index: 0,
+        }
+    }
+
+    /// Whether both arrays read the same stored elements, which a copy of an
+    /// array does until one of them is modified. Progressions store nothing.
+    pub fn shares_storage(&self, other: &Array) -> bool {
+        match (&self.storage, &other.storage) {
+            (Storage::Nested(a), Storage::Nested(b)) => Rc::ptr_eq(a, b),
+            (Storage::Flat(a), Storage::Flat(b)) => Rc::ptr_eq(&a.data, &b.data),
+            _ => false,
+        }
+    }
+
+    /// The elements for modification, copied first if they are shared. A
+    /// flat array or progression is unpacked and stays nested afterwards,
+    /// which fails with `RuntimeError::OutOfMemory` for a huge range.
+    pub fn make_mut(&mut self) -> Result<&mut Vec<Value>, RuntimeError> {
+        if self.elements().is_none() {
+            *self = Self::nested(self.unpack()?);
+        }
+
+        let Storage::Nested(value) = &mut self.storage else {
+            unreachable!("other arrays were unpacked above");
+        };
+
+        Ok(Rc::make_mut(value))
+    }
+
+    /// Takes the elements, only copying them if they are shared or not
+    /// stored as values, see `make_mut`.
+    pub fn into_vec(self) -> Result<Vec<Value>, RuntimeError> {
+        match self.storage {
+            Storage::Nested(value) => Ok(Rc::try_unwrap(value).unwrap_or_else(|v| (*v).clone())),
+            _ => self.unpack(),
+        }
+    }
+
+    /// The numbers of a flat array or progression as values.
+    fn unpack(&self) -> Result<Vec<Value>, RuntimeError> {
+        let mut values = vec![];
+        values
+            .try_reserve_exact(self.len())
+            .map_err(|_| RuntimeError::OutOfMemory(self.len()))?;
+        values.extend(self.iter());
+
+        Ok(values)
+    }
+}
+
+/// Progressions up to this length are stored when they become rows of an
+/// array, so that small ranges pack like any other row. Longer ones keep
+/// the array nested rather than being stored.
+pub(crate) const STORED_ROW_LEN: usize = 4096;
+
+/// Packs numbers, or flat arrays of one shape, into a single buffer.
+fn pack(values: &[Value]) -> Option<Flat> {
+    let mut shape = vec![values.len()];
+    let mut data = vec![];
+
+    match values.first() {
+        None => (),
+        Some(Value::Number(_)) => {
+            data.reserve(values.len());
+
+            for value in values {
+                let Value::Number(number) = value else {
+                    return None;
+                };
+                data.push(number.value);
+            }
+        }
+        Some(Value::Array(first)) => {
+            if let Some(flat) = contiguous_rows(values) {
+                return Some(flat);
+            }
+
+            let inner = first.shape()?;
+            let rows = values
+                .iter()
+                .map(|value| match value {
+                    Value::Array(array)
+                        if array.shape() == Some(inner)
+                            && (array.as_progression().is_none()
+                                || array.len() <= STORED_ROW_LEN) =>
+                    {
+                        array.numbers()
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            data.reserve(values.len() * inner.iter().product::<usize>());
+            rows.into_iter().for_each(|row| data.extend(row));
+            shape.extend_from_slice(inner);
+        }
+        Some(_) => return None,
+    }
+
+    Some(Flat {
+        shape,
+        data: Rc::new(data),
+        offset: 0,
+    })
+}
+
+/// Rows that already lie one after another in the same buffer, such as a
+/// single array wrapped in another one, are packed without copying them.
+fn contiguous_rows(values: &[Value]) -> Option<Flat> {
+    let Some(Value::Array(Array {
+        storage: Storage::Flat(first),
+    })) = values.first()
+    else {
+        return None;
+    };
+
+    let len = first.shape.iter().product::<usize>();
+    let contiguous = values.iter().enumerate().all(|(index, value)| {
+        matches!(value, Value::Array(Array { storage: Storage::Flat(row) })
+            if row.shape == first.shape
+                && Rc::ptr_eq(&row.data, &first.data)
+                && row.offset == first.offset + index * len)
+    });
+
+    contiguous.then(|| Flat {
+        shape: [&[values.len()], first.shape.as_slice()].concat(),
+        data: first.data.clone(),
+        offset: first.offset,
+    })
+}
+
+impl Default for Array {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl PartialEq for Array {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.numbers(), other.numbers()) {
+            (Some(lhs), Some(rhs)) => self.shape() == other.shape() && lhs.eq(rhs),
+            _ => self.len() == other.len() && self.iter().eq(other.iter()),
+        }
+    }
+}
+
+impl Eq for Array {}
+
+impl PartialOrd for Array {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Arrays are ordered element by element, with a prefix first.
+impl Ord for Array {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.numbers(), other.numbers()) {
+            // Rows of equal length compare like their concatenation.
+            (Some(lhs), Some(rhs)) if self.shape() == other.shape() => lhs.cmp(rhs),
+            _ => self.iter().cmp(other.iter()),
+        }
+    }
+}
+
+impl Hash for Array {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+impl From<Vec<Value>> for Array {
+    fn from(value: Vec<Value>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl FromIterator<Value> for Array {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+/// Yields the elements of an array by value. Numbers and rows of a flat
+/// array are created as they are reached.
+pub struct Iter<'a> {
+    array: &'a Array,
+    index: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let value = self.array.get(self.index)?;
+        self.index += 1;
+
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.array.len() - self.index;
+
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// Yields the elements of an array by value, moving them out of storage that
+/// is not shared and creating any others as they are reached.
+pub struct IntoIter {
+    inner: IntoIterInner,
+}
+
+enum IntoIterInner {
+    Owned(std::vec::IntoIter<Value>),
+    Shared { array: Array, index: usize },
+}
+
+impl Iterator for IntoIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match &mut self.inner {
+            IntoIterInner::Owned(iter) => iter.next(),
+            IntoIterInner::Shared { array, index } => {
+                let value = array.get(*index)?;
+                *index += 1;
+
+                Some(value)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match &self.inner {
+            IntoIterInner::Owned(iter) => iter.len(),
+            IntoIterInner::Shared { array, index } => array.len() - index,
+        };
+
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl IntoIterator for Array {
+    type Item = Value;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let inner = match self.storage {
+            Storage::Nested(value) => match Rc::try_unwrap(value) {
+                Ok(value) => IntoIterInner::Owned(value.into_iter()),
+                Err(value) => IntoIterInner::Shared {
+                    array: Self {
+                        storage: Storage::Nested(value),
+                    },
+                    index: 0,
+                },
+            },
+            storage => IntoIterInner::Shared {
+                array: Self { storage },
+                index: 0,
+            },
+        };
+
+        IntoIter { inner }
+    }
+}
+
+impl<'a> IntoIterator for &'a Array {
+    type Item = Value;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The numbers of a flat array or progression, see `Array::numbers`.
+#[derive(Clone)]
+pub struct Numbers<'a> {
+    inner: NumbersInner<'a>,
+}
+
+#[derive(Clone)]
+enum NumbersInner<'a> {
+    Flat(std::slice::Iter<'a, isize>),
+    Progression {
+        next: isize,
+        step: isize,
+        remaining: usize,
+    },
+}
+
+impl Iterator for Numbers<'_> {
+    type Item = isize;
+
+    fn next(&mut self) -> Option<isize> {
+        match &mut self.inner {
+            NumbersInner::Flat(iter) => iter.next().copied(),
+            NumbersInner::Progression {
+                next,
+                step,
+                remaining,
+            } => {
+                *remaining = remaining.checked_sub(1)?;
+                let value = *next;
+                // The step after the last element may overflow.
+                *next = next.wrapping_add(*step);
+
+                Some(value)
+            }
+        }
+    }
+
+    /// Skips ahead without stepping through a progression.
+    fn nth(&mut self, n: usize) -> Option<isize> {
+        match &mut self.inner {
+            NumbersInner::Flat(iter) => iter.nth(n).copied(),
+            NumbersInner::Progression {
+                next,
+                step,
+                remaining,
+            } => {
+                if n >= *remaining {
+                    *remaining = 0;
+                    return None;
+                }
+
+                *remaining -= n;
+                *next = next.wrapping_add(step.wrapping_mul(n as isize));
+                self.next()
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match &self.inner {
+            NumbersInner::Flat(iter) => iter.len(),
+            NumbersInner::Progression { remaining, .. } => *remaining,
+        };
+
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Numbers<'_> {}
@@ -0,0 +1,302 @@
+//! Compiles expressions into bytecode for the virtual machine in `vm`.
+//!
+//! Each lambda becomes its own chunk. Named parameters are resolved here to
+//! the same argument slots `$0`, `$$1`, ... refer to, so the machine never
+//! looks names up in environments.
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::parser::{Expr, Lambda};
+
+use super::{
+    error::RuntimeError,
+    operation::{Operation, Operator},
+    Value,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Instruction {
+    /// Pushes `constants[i]`.
+    Constant(usize),
+    /// Pushes the global variable `names[i]`.
+    LoadVar(usize),
+    /// Pushes argument `index` of the lambda `depth` levels out.
+    LoadArg {
+        depth: usize,
+        index: usize,
+    },
+    /// Moves argument `index` of this lambda onto the stack, where it is read
+    /// for the last time, so that a map or array passed along can be updated
+    /// without copying it.
+    TakeArg(usize),
+    /// Binds the value on top of the stack to `names[i]`, leaving it there.
+    StoreVar(usize),
+    /// Pushes the function `names[i]` as a value.
+    Function(usize),
+    /// Pushes the argument shadowing the function `name`, if it holds one,
+    /// and the function otherwise.
+    ArgFunction {
+        depth: usize,
+        index: usize,
+        name: usize,
+    },
+    /// Pushes a closure over `lambdas[i]` and the current arguments.
+    Closure(usize),
+    /// Defines `names[name]` as a closure over `lambdas[lambda]` and pushes
+    /// it.
+    Define {
+        name: usize,
+        lambda: usize,
+    },
+    /// Pops `n` values into an array.
+    Array(usize),
+    /// Pops `n` key-value pairs into a map.
+    Map(usize),
+    Binary(Operation),
+    Unary(Operation),
+    /// Pops the argument and then the function and pushes the result.
+    Call,
+    /// Pops the condition and jumps if it is false.
+    JumpIfFalse(usize),
+    Jump(usize),
+    /// Replaces a scalar left operand of `||` or `&&` that decides the result
+    /// with it and jumps past the right operand.
+    ShortCircuit(Operator, usize),
+}
+
+/// Compiled code for a top level expression or a lambda body.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub lambdas: Vec<Rc<Chunk>>,
+    /// Number of named parameters, 0 for lambdas using `$0`, `$1`, ...
+    pub params: usize,
+}
+
+pub fn compile(expr: &Expr) -> Result<Chunk, RuntimeError> {
+    let mut compiler = Compiler {
+        chunk: Chunk::default(),
+        scopes: vec![],
+    };
+    compiler.expr(expr)?;
+
+    Ok(compiler.chunk)
+}
+
+struct Compiler {
+    chunk: Chunk,
+    /// Parameter names of the enclosing lambdas, innermost last.
+    scopes: Vec<Vec<String>>,
+}
+
+impl Compiler {
+    fn expr(&mut self, expr: &Expr) -> Result<(), RuntimeError> {
+        match expr {
+            Expr::Binary(op, lhs, rhs) => {
+                let op = operation(op)?;
+                self.expr(lhs)?;
+
+                match op.operator {
+                    Operator::Or | Operator::And => {
+                        let short_circuit = self.emit(Instruction::ShortCircuit(op.operator, 0));
+                        self.expr(rhs)?;
+                        self.emit(Instruction::Binary(op));
+
+                        let end = self.chunk.code.len();
+                        self.chunk.code[short_circuit] =
+                            Instruction::ShortCircuit(op.operator, end);
+                    }
+                    _ => {
+                        self.expr(rhs)?;
+                        self.emit(Instruction::Binary(op));
+                    }
+                }
+            }
+            Expr::Unary(op, val) => {
+                let op = operation(op)?;
+                self.expr(val)?;
+                self.emit(Instruction::Unary(op));
+            }
+            Expr::Variable(var) => {
+                let instruction = match self.resolve(&var.name) {
+                    Some((depth, index)) => Instruction::LoadArg { depth, index },
+                    None => Instruction::LoadVar(self.name(&var.name)),
+                };
+                self.emit(instruction);
+            }
+            Expr::Number(number) => {
+                self.constant(Value::Number(number.clone()));
+            }
+            Expr::String(string) => {
+                self.constant(Value::String(string.clone()));
+            }
+            Expr::Array(arr) => {
+                let len = arr.len();
+                for element in arr {
+                    self.expr(element)?;
+                }
+                self.emit(Instruction::Array(len));
+            }
+            Expr::Map(map) => {
+                let len = map.len();
+                for (key, value) in map {
+                    self.expr(key)?;
+                    self.expr(value)?;
+                }
+                self.emit(Instruction::Map(len));
+            }
+            Expr::Lambda(lambda) => {
+                let lambda = self.lambda(lambda)?;
+                self.emit(Instruction::Closure(lambda));
+            }
+            Expr::Function(ident) => {
+                let instruction = match self.resolve(&ident.name) {
+                    Some((depth, index)) => Instruction::ArgFunction {
+                        depth,
+                        index,
+                        name: self.name(&ident.name),
+                    },
+                    None => Instruction::Function(self.name(&ident.name)),
+                };
+                self.emit(instruction);
+            }
+            Expr::Call(function, args) => {
+                self.expr(function)?;
+                self.expr(args)?;
+                self.emit(Instruction::Call);
+            }
+            Expr::Assign(ident, expr) => {
+                self.expr(expr)?;
+                let name = self.name(&ident.name);
+                self.emit(Instruction::StoreVar(name));
+            }
+            Expr::Define(definition) => {
+                let lambda = self.lambda(&definition.lambda)?;
+                let name = self.name(&definition.name.name);
+                self.emit(Instruction::Define { name, lambda });
+            }
+            Expr::Conditional(conditional) => {
+                self.expr(&conditional.condition)?;
+                let jump_to_otherwise = self.emit(Instruction::JumpIfFalse(0));
+
+                self.expr(&conditional.then)?;
+                let jump_to_end = self.emit(Instruction::Jump(0));
+
+                self.chunk.code[jump_to_otherwise] =
+                    Instruction::JumpIfFalse(self.chunk.code.len());
+                self.expr(&conditional.otherwise)?;
+                self.chunk.code[jump_to_end] = Instruction::Jump(self.chunk.code.len());
+            }
+            Expr::Argument(arg) => {
+                self.emit(Instruction::LoadArg {
+                    depth: arg.depth,
+                    index: arg.index,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a lambda body into its own chunk, returning its index.
+    fn lambda(&mut self, lambda: &Lambda) -> Result<usize, RuntimeError> {
+        let params: Vec<String> = lambda.params.iter().map(|p| p.name.clone()).collect();
+
+        let mut compiler = Compiler {
+            chunk: Chunk {
+                params: params.len(),
+                ..Default::default()
+            },
+            scopes: std::mem::take(&mut self.scopes),
+        };
+        compiler.scopes.push(params);
+
+        let result = compiler.expr(&lambda.body);
+        compiler.scopes.pop();
+        self.scopes = std::mem::take(&mut compiler.scopes);
+        result?;
+
+        take_last_reads(&mut compiler.chunk);
+        self.chunk.lambdas.push(Rc::new(compiler.chunk));
+
+        Ok(self.chunk.lambdas.len() - 1)
+    }
+
+    /// The argument slot of a named parameter, searching the innermost
+    /// lambda first.
+    fn resolve(&self, name: &str) -> Option<(usize, usize)> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, params)| Some((depth, params.iter().position(|p| p == name)?)))
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.code.len() - 1
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        self.chunk.constants.push(value);
+        let index = self.chunk.constants.len() - 1;
+
+        self.emit(Instruction::Constant(index))
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        match self.chunk.names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                self.chunk.names.push(name.to_owned());
+                self.chunk.names.len() - 1
+            }
+        }
+    }
+}
+
+/// Turns reads of an argument into moves where nothing can read it again.
+/// Jumps only go forward, so an argument read in one place is read at most
+/// once per call. Arguments of a chunk creating closures are kept, since the
+/// closures may read them later.
+fn take_last_reads(chunk: &mut Chunk) {
+    let captures = chunk.code.iter().any(|instruction| {
+        matches!(
+            instruction,
+            Instruction::Closure(_) | Instruction::Define { .. }
+        )
+    });
+
+    if captures {
+        return;
+    }
+
+    let mut reads: HashMap<usize, usize> = HashMap::new();
+    for instruction in &chunk.code {
+        if let Instruction::LoadArg { depth: 0, index }
+        | Instruction::ArgFunction {
+            depth: 0, index, ..
+        } = instruction
+        {
+            *reads.entry(*index).or_default() += 1;
+        }
+    }
+
+    for instruction in &mut chunk.code {
+        if let Instruction::LoadArg { depth: 0, index } = *instruction {
+            if reads[&index] == 1 {
+                *instruction = Instruction::TakeArg(index);
+            }
+        }
+    }
+}
+
+fn operation(op: &crate::parser::Operator) -> Result<Operation, RuntimeError> {
+    Ok(Operation {
+        operator: Operator::from_symbol(&op.name)
+            .ok_or_else(|| RuntimeError::UndefinedOperator(op.name.clone()))?,
+        modifier: op.modifiers,
+    })
+}
@@ -0,0 +1,78 @@
+//! Conversions between values and Rust types for host applications.
+//! Converting a value fails with a type mismatch when it has the wrong type
+//! anywhere inside it, and converting an array too large to store fails with
+//! `RuntimeError::OutOfMemory`.
+
+use crate::parser::Number;
+
+use super::{
+    error::{RuntimeError, TypeMismatchError},
+    Value,
+};
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Number(Number {
+            value: value as isize,
+        })
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = TypeMismatchError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(number) => Ok(number.value as i64),
+            _ => Err(TypeMismatchError::new("number", value.type_name())),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = TypeMismatchError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(string) => Ok(string),
+            _ => Err(TypeMismatchError::new("string", value.type_name())),
+        }
+    }
+}
+
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value>,
+    RuntimeError: From<T::Error>,
+{
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(arr) => arr
+                .into_vec()?
+                .into_iter()
+                .map(|v| Ok(T::try_from(v)?))
+                .collect(),
+            _ => Err(TypeMismatchError::new("array", value.type_name()).into()),
+        }
+    }
+}
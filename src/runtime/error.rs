@@ -1 +1,104 @@
+use std::fmt::Display;
 
+use super::limits::Limit;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    UndefinedVariable(String),
+    UndefinedFunction(String),
+    UndefinedOperator(String),
+    UnboundArgument(usize),
+    NotCallable(&'static str),
+    TypeMismatch(TypeMismatchError),
+    ArgumentCount(ArgumentCountError),
+    KeyNotFound(String),
+    EmptyReduction,
+    DivisionByZero,
+    Overflow,
+    RecursionLimit(usize),
+    LimitExceeded(Limit),
+    OutOfMemory(usize),
+    Interrupted,
+    Input(String),
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::UndefinedVariable(name) => write!(f, "Undefined variable {}", name),
+            RuntimeError::UndefinedFunction(name) => write!(f, "Undefined function {}", name),
+            RuntimeError::UndefinedOperator(name) => write!(f, "Undefined operator {}", name),
+            RuntimeError::UnboundArgument(index) => {
+                write!(f, "Argument ${} is not bound", index)
+            }
+            RuntimeError::NotCallable(found) => write!(f, "Cannot call a {}", found),
+            RuntimeError::TypeMismatch(err) => {
+                write!(f, "Expected {}, found {}", err.expected, err.found)
+            }
+            RuntimeError::ArgumentCount(err) => {
+                write!(
+                    f,
+                    "Expected {} arguments, found {}",
+                    err.expected, err.found
+                )
+            }
+            RuntimeError::KeyNotFound(key) => write!(f, "Key {} not found", key),
+            RuntimeError::EmptyReduction => write!(f, "Cannot reduce an empty array"),
+            RuntimeError::DivisionByZero => write!(f, "Division by zero"),
+            RuntimeError::Overflow => write!(f, "Number too large"),
+            RuntimeError::RecursionLimit(depth) => {
+                write!(f, "Maximum recursion depth of {} exceeded", depth)
+            }
+            RuntimeError::LimitExceeded(Limit::Steps(max)) => {
+                write!(f, "Evaluation exceeded {} steps", max)
+            }
+            RuntimeError::LimitExceeded(Limit::Elements(max)) => {
+                write!(f, "Evaluation exceeded {} array elements", max)
+            }
+            RuntimeError::LimitExceeded(Limit::Timeout(timeout)) => {
+                write!(f, "Evaluation exceeded the timeout of {:?}", timeout)
+            }
+            RuntimeError::OutOfMemory(len) => {
+                write!(f, "Not enough memory for {} array elements", len)
+            }
+            RuntimeError::Interrupted => write!(f, "Interrupted"),
+            RuntimeError::Input(err) => write!(f, "Failed to read input: {}", err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeMismatchError {
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+impl TypeMismatchError {
+    pub fn new(expected: &'static str, found: &'static str) -> Self {
+        Self { expected, found }
+    }
+}
+
+impl From<TypeMismatchError> for RuntimeError {
+    fn from(value: TypeMismatchError) -> Self {
+        Self::TypeMismatch(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentCountError {
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl ArgumentCountError {
+    pub fn new(expected: usize, found: usize) -> Self {
+        Self { expected, found }
+    }
+}
+
+impl From<ArgumentCountError> for RuntimeError {
+    fn from(value: ArgumentCountError) -> Self {
+        Self::ArgumentCount(value)
+    }
+}
@@ -0,0 +1,123 @@
+use std::{cell::RefCell, cmp::Ordering, fmt::Display, hash::Hash, rc::Rc};
+
+use super::{compiler::Chunk, error::RuntimeError, Array, Value};
+
+/// A callable value. Lambdas have no meaningful structural equality, so they
+/// compare by identity, which still lets them be used as map keys.
+#[derive(Clone, Debug)]
+pub enum Function {
+    Lambda(Closure),
+    Builtin(String),
+    Native(Rc<Native>),
+}
+
+type NativeBody = Box<dyn Fn(Vec<Value>) -> Result<Value, RuntimeError>>;
+
+/// A function implemented by the host application, see
+/// `Runtime::register_fn`.
+pub struct Native {
+    pub name: String,
+    pub arity: usize,
+    pub body: NativeBody,
+}
+
+impl std::fmt::Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A compiled lambda together with the arguments of the lambdas it was
+/// defined in.
+#[derive(Clone, Debug)]
+pub struct Closure {
+    pub chunk: Rc<Chunk>,
+    pub env: Option<Rc<Env>>,
+}
+
+impl Closure {
+    fn as_ptr(&self) -> (*const Chunk, *const Env) {
+        (
+            Rc::as_ptr(&self.chunk),
+            self.env.as_ref().map_or(std::ptr::null(), Rc::as_ptr),
+        )
+    }
+}
+
+/// The arguments of one lambda invocation. `$0` and named parameters read
+/// from the innermost environment, `$$0` and parameters of enclosing lambdas
+/// from its parents.
+#[derive(Debug)]
+pub struct Env {
+    /// Arguments read for the last time are moved out, see
+    /// `Instruction::TakeArg`.
+    pub args: RefCell<Array>,
+    pub parent: Option<Rc<Env>>,
+}
+
+impl Env {
+    pub fn ancestor(&self, depth: usize) -> Option<&Env> {
+        match depth {
+            0 => Some(self),
+            _ => self.parent.as_ref()?.ancestor(depth - 1),
+        }
+    }
+}
+
+impl Function {
+    fn discriminant(&self) -> u8 {
+        match self {
+            Function::Lambda(_) => 0,
+            Function::Builtin(_) => 1,
+            Function::Native(_) => 2,
+        }
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Function {}
+
+impl PartialOrd for Function {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Function {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Function::Lambda(lhs), Function::Lambda(rhs)) => lhs.as_ptr().cmp(&rhs.as_ptr()),
+            (Function::Builtin(lhs), Function::Builtin(rhs)) => lhs.cmp(rhs),
+            (Function::Native(lhs), Function::Native(rhs)) => Rc::as_ptr(lhs).cmp(&Rc::as_ptr(rhs)),
+            _ => self.discriminant().cmp(&other.discriminant()),
+        }
+    }
+}
+
+impl Hash for Function {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self {
+            Function::Lambda(closure) => closure.as_ptr().hash(state),
+            Function::Builtin(name) => name.hash(state),
+            Function::Native(native) => Rc::as_ptr(native).hash(state),
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Function::Lambda(_) => write!(f, "$:<lambda>"),
+            Function::Builtin(name) => write!(f, "${}", name),
+            Function::Native(native) => write!(f, "${}", native.name),
+        }
+    }
+}
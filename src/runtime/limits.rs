@@ -0,0 +1,143 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{error::RuntimeError, MAX_DEPTH};
+
+/// Bounds on the work a single evaluation may do, for running untrusted
+/// scripts. An evaluation lasts from `Runtime::start` to the next one, which
+/// `eval_str` calls once for its whole source. Evaluation stops with
+/// `RuntimeError::LimitExceeded` once one is reached, or
+/// `RuntimeError::RecursionLimit` for the depth.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Expressions evaluated and elements combined by operators, where
+    /// loops over flat arrays count a step per `NUMBERS_PER_STEP` numbers.
+    pub max_steps: Option<u64>,
+    /// Array elements created, counted before they are. Ranges and the
+    /// results of adding, subtracting or multiplying them by a number are
+    /// only counted once something needs their elements in memory.
+    pub max_elements: Option<usize>,
+    /// Nested lambda calls. Each one uses native stack, so fewer are allowed
+    /// when the thread is about to run out of it. Reaching `MAX_DEPTH` needs
+    /// a thread with `STACK_SIZE`.
+    pub max_depth: usize,
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_steps: None,
+            max_elements: None,
+            max_depth: MAX_DEPTH,
+            timeout: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Steps(u64),
+    Elements(usize),
+    Timeout(Duration),
+}
+
+/// The timeout is only checked every this many steps, a power of two, since
+/// reading the clock is slower than evaluating most expressions.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Loops over the numbers of flat arrays and progressions count one step for
+/// this many of them, a power of two, so that they stay tight.
+const NUMBERS_PER_STEP: u64 = 1024;
+
+/// What is left of the limits during one evaluation, and the flag that
+/// interrupts it.
+pub struct Budget {
+    pub limits: Limits,
+    pub interrupt: Arc<AtomicBool>,
+    steps: u64,
+    numbers: u64,
+    elements: usize,
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    /// A budget that starts right away.
+    pub fn new(limits: Limits) -> Self {
+        let mut budget = Self {
+            limits,
+            interrupt: Arc::new(AtomicBool::new(false)),
+            steps: 0,
+            numbers: 0,
+            elements: 0,
+            deadline: None,
+        };
+        budget.start();
+
+        budget
+    }
+
+    /// Resets the counters at the start of an evaluation.
+    pub fn start(&mut self) {
+        self.steps = 0;
+        self.numbers = 0;
+        self.elements = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// Accounts for one step, failing once a limit is reached or the flag
+    /// is set. The flag is cleared when that is reported.
+    pub fn step(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+
+        if self.interrupt.load(Ordering::Relaxed) {
+            self.interrupt.store(false, Ordering::Relaxed);
+            return Err(RuntimeError::Interrupted);
+        }
+
+        if let Some(max) = self.limits.max_steps {
+            if self.steps > max {
+                return Err(RuntimeError::LimitExceeded(Limit::Steps(max)));
+            }
+        }
+
+        if self.steps & (CLOCK_INTERVAL - 1) == 0 {
+            if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout) {
+                if Instant::now() > deadline {
+                    return Err(RuntimeError::LimitExceeded(Limit::Timeout(timeout)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accounts for one number read by a loop over a flat array or
+    /// progression, see `NUMBERS_PER_STEP`.
+    pub fn read_number(&mut self) -> Result<(), RuntimeError> {
+        self.numbers += 1;
+
+        if self.numbers & (NUMBERS_PER_STEP - 1) == 0 {
+            self.step()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Accounts for `count` new array elements before they are created.
+    pub fn allocate(&mut self, count: usize) -> Result<(), RuntimeError> {
+        self.elements = self.elements.saturating_add(count);
+
+        match self.limits.max_elements {
+            Some(max) if self.elements > max => {
+                Err(RuntimeError::LimitExceeded(Limit::Elements(max)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
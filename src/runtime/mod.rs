@@ -1,175 +1,967 @@
+pub mod array;
+pub mod compiler;
+mod convert;
 pub mod error;
-pub mod number;
+pub mod function;
+pub mod limits;
+// Arbitrary precision numbers, which evaluation does not use yet.
+#[allow(dead_code)]
+pub(crate) mod number;
 pub mod operation;
+mod stack;
+mod vm;
 
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    rc::Rc,
+    sync::{atomic::AtomicBool, Arc},
+};
 
 use crate::{
     library,
     parser::{Expr, Number},
 };
 
-use self::operation::{operate, Modifier, Operation, Operator};
+pub use self::array::Array;
+
+use self::{
+    array::STORED_ROW_LEN,
+    error::{ArgumentCountError, RuntimeError, TypeMismatchError},
+    function::{Env, Function, Native},
+    limits::{Budget, Limits},
+    operation::{operate, operate_scalar, Modifier, Operation, Operator},
+};
+
+/// Maximum number of nested lambda calls before evaluation is aborted. Calls
+/// also stop early, with the same error, once the thread is about to run out
+/// of stack.
+pub const MAX_DEPTH: usize = 5000;
+
+type LazyValue = Box<dyn FnOnce() -> Result<Value, RuntimeError>>;
 
 pub struct Runtime {
     variables: HashMap<String, Value>,
+    lazy_variables: HashMap<String, LazyValue>,
+    functions: HashMap<String, Function>,
+    depth: usize,
+    budget: Budget,
+    stack: Vec<Value>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Runtime {
     pub fn new() -> Self {
         Self {
             variables: HashMap::new(),
+            lazy_variables: HashMap::new(),
+            functions: library::BUILTINS
+                .iter()
+                .map(|builtin| {
+                    let name = builtin.name.to_owned();
+                    (name.clone(), Function::Builtin(name))
+                })
+                .collect(),
+            depth: 0,
+            budget: Budget::new(Limits::default()),
+            stack: vec![],
         }
     }
 
+    /// A flag that stops the running evaluation with
+    /// `RuntimeError::Interrupted` once set, from any thread. It is cleared
+    /// once that is reported, so setting it between evaluations stops the
+    /// next one.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        self.budget.interrupt.clone()
+    }
+
+    /// Applies `limits` to every following evaluation, starting one right
+    /// away.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget.limits = limits;
+        self.budget.start();
+    }
+
+    /// Starts a new evaluation. The expressions evaluated until the next
+    /// start share one budget of the limits, so a script cannot escape them
+    /// by spreading its work over many lines.
+    pub fn start(&mut self) {
+        self.budget.start();
+    }
+
+    /// Makes a Rust function callable from scripts as `name`. It receives
+    /// exactly `arity` arguments, and calls with any other number fail.
+    ///
+    /// ```
+    /// use vohtori::{eval_str, Runtime, Value};
+    ///
+    /// let mut runtime = Runtime::new();
+    /// runtime.register_fn("scale", 2, |args| {
+    ///     let factor = i64::try_from(args[1].clone())?;
+    ///     let values = Vec::<i64>::try_from(args[0].clone())?;
+    ///
+    ///     Ok(values.into_iter().map(|v| v * factor).collect::<Vec<_>>().into())
+    /// });
+    ///
+    /// assert_eq!(
+    ///     eval_str(&mut runtime, "scale: [[1 2 3] 2]").unwrap(),
+    ///     Value::from(vec![2i64, 4, 6])
+    /// );
+    /// ```
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        arity: usize,
+        body: impl Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static,
+    ) {
+        let native = Native {
+            name: name.to_owned(),
+            arity,
+            body: Box::new(body),
+        };
+
+        self.functions
+            .insert(name.to_owned(), Function::Native(Rc::new(native)));
+    }
+
+    /// Binds a variable, replacing any lazy binding of the same name.
     pub fn push_var(&mut self, name: &str, val: Value) {
+        self.lazy_variables.remove(name);
         self.variables.insert(name.to_owned(), val);
     }
 
-    pub fn eval_expr(&mut self, expr: Expr, body_args: Option<&Array>) -> Value {
-        match expr {
-            Expr::Binary(op, lhs, rhs) => {
-                let op = Operation {
-                    operator: Operator::from_str(&op.name).unwrap(),
-                    modifier: op.modifiers,
-                };
-                let lhs = self.eval_expr(*lhs, body_args);
-                let rhs = self.eval_expr(*rhs, body_args);
+    /// Binds a variable whose value is only computed the first time it is
+    /// referenced.
+    pub fn push_lazy_var(
+        &mut self,
+        name: &str,
+        val: impl FnOnce() -> Result<Value, RuntimeError> + 'static,
+    ) {
+        self.lazy_variables.insert(name.to_owned(), Box::new(val));
+    }
+
+    /// The bound variables, with lazy ones that have not been read yet as
+    /// `None`.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, Option<&Value>)> {
+        self.variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), Some(value)))
+            .chain(self.lazy_variables.keys().map(|name| (name.as_str(), None)))
+    }
+
+    /// The callable functions by name: builtins, natives added with
+    /// [`register_fn`](Self::register_fn) and those defined with `fn`.
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &Function)> {
+        self.functions
+            .iter()
+            .map(|(name, function)| (name.as_str(), function))
+    }
+
+    /// Forgets every variable and function defined so far. Lazy variables
+    /// that have not been read are kept, since they cannot be rebound, as are
+    /// functions added with [`register_fn`](Self::register_fn), the limits
+    /// and the interrupt flag.
+    pub fn reset(&mut self) {
+        let lazy_variables = std::mem::take(&mut self.lazy_variables);
+        let budget = std::mem::replace(&mut self.budget, Budget::new(Limits::default()));
+        let natives: Vec<_> = std::mem::take(&mut self.functions)
+            .into_iter()
+            .filter(|(_, function)| matches!(function, Function::Native(_)))
+            .collect();
+
+        *self = Self::new();
+        self.lazy_variables = lazy_variables;
+        self.budget = budget;
+        self.functions.extend(natives);
+    }
+
+    fn get_var(&mut self, name: String) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.variables.get(&name) {
+            return Ok(value.clone());
+        }
+
+        let lazy = self
+            .lazy_variables
+            .remove(&name)
+            .ok_or_else(|| RuntimeError::UndefinedVariable(name.clone()))?;
+        let value = lazy()?;
+        self.push_var(&name, value.clone());
+
+        Ok(value)
+    }
+
+    /// Compiles and runs one top level expression within what is left of the
+    /// limits since the last `start`. The expression is only borrowed, so the
+    /// same tree can be evaluated again.
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        let chunk = compiler::compile(expr)?;
+
+        self.run(&chunk, None)
+    }
 
-                apply(op, lhs, rhs)
+    /// Functions defined with `fn` and the builtins, which function values in
+    /// parameters and variables shadow.
+    fn lookup_function(&self, name: &str) -> Result<Function, RuntimeError> {
+        self.functions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RuntimeError::UndefinedFunction(name.to_owned()))
+    }
+
+    /// Calls a function with a single argument value, a lambda receives the
+    /// elements of an array argument as `$0`, `$1`, ... A lambda with one
+    /// named parameter or a native function of one argument receives the
+    /// value as is.
+    pub fn call(&mut self, function: &Function, arg: Value) -> Result<Value, RuntimeError> {
+        match function {
+            Function::Lambda(closure) if closure.chunk.params == 1 => {
+                self.call_with(function, vec![arg])
             }
-            Expr::Unary(op, val) => {
-                let op = Operation {
-                    operator: Operator::from_str(&op.name).unwrap(),
-                    modifier: op.modifiers,
-                };
-                let val = self.eval_expr(*val, body_args);
-
-                apply_unary(op, val)
-            }
-            Expr::Variable(var) => self.variables.get(&var.name).unwrap().clone(),
-            Expr::Number(i) => Value::Number(i),
-            Expr::Array(arr) => Value::Array(Array {
-                value: arr
-                    .into_iter()
-                    .map(|e| self.eval_expr(e, body_args))
-                    .collect(),
-            }),
-            Expr::Call(function, args) => match *function {
-                Expr::Function(ident) => {
-                    let args = self.eval_expr(*args, body_args);
-
-                    match ident.name.as_str() {
-                        "idx" => library::index(args),
-                        _ => panic!()
-                    }
+            Function::Lambda(_) => {
+                let args = self.arguments(arg)?;
+                self.call_with(function, args)
+            }
+            Function::Builtin(name) => self.call_builtin(name, arg),
+            Function::Native(native) if native.arity == 1 => (native.body)(vec![arg]),
+            Function::Native(_) => {
+                let args = self.arguments(arg)?;
+                self.call_with(function, args)
+            }
+        }
+    }
+
+    /// The elements of an array argument as values.
+    fn arguments(&mut self, arg: Value) -> Result<Vec<Value>, RuntimeError> {
+        self.budget.allocate(lazy_elements(&arg))?;
+
+        arg.into_array().into_vec()
+    }
+
+    /// Calls a function with an explicit argument list, as done by higher
+    /// order builtins.
+    fn call_with(
+        &mut self,
+        function: &Function,
+        mut args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        match function {
+            Function::Lambda(closure) => {
+                let params = closure.chunk.params;
+
+                if params > 0 && params != args.len() {
+                    return Err(ArgumentCountError::new(params, args.len()).into());
                 }
-                Expr::Lambda(lambda) => {
-                    let args = self.eval_expr(*args, body_args);
-                    self.eval_expr(*lambda.body, Some(&args.into_array()))
+
+                let max_depth = self.budget.limits.max_depth;
+                if self.depth >= max_depth {
+                    return Err(RuntimeError::RecursionLimit(max_depth));
                 }
-                _ => {
-                    todo!()
+                if stack::exhausted() {
+                    return Err(RuntimeError::RecursionLimit(self.depth));
                 }
-            },
-            Expr::Argument(arg) => {
-                if let Some(arg_env) = body_args {
-                    arg_env.value[arg.index].clone()
-                } else {
-                    panic!()
+
+                let env = Rc::new(Env {
+                    args: RefCell::new(Array::new(args)),
+                    parent: closure.env.clone(),
+                });
+
+                self.depth += 1;
+                let value = self.run(&closure.chunk, Some(&env));
+                self.depth -= 1;
+
+                value
+            }
+            Function::Builtin(name) => {
+                let arg = match args.len() {
+                    1 => args.pop().unwrap(),
+                    _ => Value::Array(Array::new(args)),
+                };
+
+                self.call_builtin(name, arg)
+            }
+            Function::Native(native) => {
+                if native.arity != args.len() {
+                    return Err(ArgumentCountError::new(native.arity, args.len()).into());
                 }
+
+                (native.body)(args)
+            }
+        }
+    }
+
+    fn call_builtin(&mut self, name: &str, args: Value) -> Result<Value, RuntimeError> {
+        match name {
+            "idx" => {
+                self.budget.allocate(nested_lazy_elements(&args))?;
+                library::index(args)
+            }
+            "take" => library::take(args),
+            "get" => library::get(args),
+            "insert" => library::insert(args),
+            "keys" => library::keys(args),
+            "values" => library::values(args),
+            "group_by" => {
+                let (args, [f]) = split_functions(args)?;
+                self.budget.allocate(lazy_elements(&args))?;
+                library::group_by(args, |v| self.call_with(&f, vec![v]))
+            }
+            "each" => {
+                let (args, [f]) = split_functions(args)?;
+                library::each(args, |v| {
+                    self.budget.allocate(1)?;
+                    let v = self.call_with(&f, vec![v])?;
+                    self.budget.allocate(row_elements(&v))?;
+
+                    Ok(v)
+                })
             }
-            _ => panic!(),
+            "fold" => {
+                let (args, [f]) = split_functions(args)?;
+                library::fold(args, |acc, v| self.call_with(&f, vec![acc, v]))
+            }
+            "scan" => {
+                let (args, [f]) = split_functions(args)?;
+                library::scan(args, |acc, v| {
+                    self.budget.allocate(1)?;
+                    let v = self.call_with(&f, vec![acc, v])?;
+                    self.budget.allocate(row_elements(&v))?;
+
+                    Ok(v)
+                })
+            }
+            "iterate" => {
+                let (args, [f]) = split_functions(args)?;
+                library::iterate(args, |v| {
+                    self.budget.allocate(1)?;
+                    let v = self.call_with(&f, vec![v])?;
+                    self.budget.allocate(row_elements(&v))?;
+
+                    Ok(v)
+                })
+            }
+            "until" => {
+                let (args, [pred, f]) = split_functions(args)?;
+                library::until(args, |v| {
+                    if self.call_with(&pred, vec![v.clone()])?.is_true()? {
+                        Ok(None)
+                    } else {
+                        Ok(Some(self.call_with(&f, vec![v])?))
+                    }
+                })
+            }
+            "fixpoint" => {
+                let (args, [f]) = split_functions(args)?;
+                library::fixpoint(args, |v| self.call_with(&f, vec![v]))
+            }
+            _ => Err(RuntimeError::UndefinedFunction(name.to_owned())),
         }
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Array {
-    pub value: Vec<Value>,
+/// Splits the `N` trailing functions off the arguments of a higher order
+/// builtin written as `[args... f]`. A single remaining argument is passed as
+/// is.
+fn split_functions<const N: usize>(args: Value) -> Result<(Value, [Function; N]), RuntimeError> {
+    let args = args.into_array();
+    let len = args.len();
+
+    if len < N {
+        return Err(ArgumentCountError::new(N, len).into());
+    }
+
+    // The functions are checked first, so that an array which is not an
+    // argument list is not unpacked.
+    let functions = (len - N..len)
+        .map(|i| match args.get(i).unwrap() {
+            Value::Function(function) => Ok(function),
+            v => Err(TypeMismatchError::new("function", v.type_name()).into()),
+        })
+        .collect::<Result<Vec<_>, RuntimeError>>()?
+        .try_into()
+        .unwrap();
+
+    let mut args = args.into_vec()?;
+    args.truncate(len - N);
+
+    let args = match args.len() {
+        1 => args.pop().unwrap(),
+        _ => Value::Array(Array::new(args)),
+    };
+
+    Ok((args, functions))
+}
+
+/// Keys are kept ordered so that printing a map is deterministic. Like the
+/// elements of an array, the entries are shared between copies of a map and
+/// only copied when a map that is still shared is modified.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Map {
+    value: Rc<BTreeMap<Value, Value>>,
+}
+
+impl Map {
+    pub fn new(value: BTreeMap<Value, Value>) -> Self {
+        Self {
+            value: Rc::new(value),
+        }
+    }
+
+    pub fn entries(&self) -> &BTreeMap<Value, Value> {
+        &self.value
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// The entries for modification, copied first if they are shared.
+    pub fn make_mut(&mut self) -> &mut BTreeMap<Value, Value> {
+        Rc::make_mut(&mut self.value)
+    }
+
+    /// Takes the entries, only copying them if they are shared.
+    pub fn into_entries(self) -> BTreeMap<Value, Value> {
+        Rc::unwrap_or_clone(self.value)
+    }
+
+    /// Whether both maps read the same stored entries, which a copy of a map
+    /// does until one of them is modified.
+    pub fn shares_storage(&self, other: &Map) -> bool {
+        Rc::ptr_eq(&self.value, &other.value)
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Value {
     Array(Array),
+    Map(Map),
     Number(Number),
+    String(String),
+    Function(Function),
 }
 
 impl Value {
-    fn into_array(self) -> Array {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Function(_) => "function",
+        }
+    }
+
+    /// Truthiness of a scalar, anything nonzero is true.
+    pub fn is_true(&self) -> Result<bool, RuntimeError> {
+        match self {
+            Value::Number(number) => Ok(number.value != 0),
+            _ => Err(TypeMismatchError::new("number", self.type_name()).into()),
+        }
+    }
+
+    pub fn into_array(self) -> Array {
         if let Value::Array(array) = self {
             array
         } else {
-            Array { value: vec![self] }
+            Array::new(vec![self])
         }
     }
 }
 
-fn apply(op: Operation, lhs: Value, rhs: Value) -> Value {
-    match (lhs, rhs) {
-        (Value::Array(lhs), Value::Number(rhs)) => {
-            let output = lhs
-                .value
-                .into_iter()
-                .map(|v| apply(op, v, Value::Number(rhs.clone())))
-                .collect();
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Array(array) => {
+                write!(f, "[")?;
 
-            Value::Array(Array { value: output })
-        }
-        (Value::Number(lhs), Value::Array(rhs)) => {
-            let output = rhs
-                .value
-                .into_iter()
-                .map(|v| apply(op, Value::Number(lhs.clone()), v))
-                .collect();
+                for (i, v) in array.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+
+                    write!(f, "{}", v)?;
+                }
 
-            Value::Array(Array { value: output })
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+
+                for (i, (k, v)) in map.entries().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+
+                    write!(f, "{}: {}", k, v)?;
+                }
+
+                write!(f, "}}")
+            }
+            Value::Number(number) => write!(f, "{}", number.value),
+            Value::String(string) => write!(f, "{:?}", string),
+            Value::Function(function) => write!(f, "{}", function),
         }
+    }
+}
+
+fn apply(
+    op: Operation,
+    lhs: Value,
+    rhs: Value,
+    budget: &mut Budget,
+) -> Result<Value, RuntimeError> {
+    if let Some(value) = apply_flat(op, &lhs, &rhs, budget)? {
+        return Ok(value);
+    }
+
+    Ok(match (lhs, rhs) {
         (Value::Array(lhs), Value::Array(rhs)) => {
             let output = if op.modifier.contains(Modifier::Table) {
-                lhs.value
-                    .into_iter()
+                budget.allocate(lhs.len().saturating_mul(rhs.len()))?;
+
+                lhs.iter()
                     .map(|lhs| {
-                        Value::Array(Array {
-                            value: rhs
-                                .value
-                                .iter()
-                                .map(|rhs| apply(op, lhs.clone(), rhs.clone()))
-                                .collect(),
-                        })
+                        Ok(Value::Array(
+                            rhs.iter()
+                                .map(|rhs| {
+                                    budget.step()?;
+                                    apply(op, lhs.clone(), rhs, budget)
+                                })
+                                .collect::<Result<_, _>>()?,
+                        ))
                     })
-                    .collect()
+                    .collect::<Result<_, RuntimeError>>()?
             } else {
-                lhs.value
-                    .into_iter()
-                    .zip(rhs.value.into_iter())
-                    .map(|(lhs, rhs)| apply(op, lhs, rhs))
-                    .collect()
+                budget.allocate(lhs.len().min(rhs.len()))?;
+
+                lhs.into_iter()
+                    .zip(rhs)
+                    .map(|(lhs, rhs)| {
+                        budget.step()?;
+                        apply(op, lhs, rhs, budget)
+                    })
+                    .collect::<Result<_, _>>()?
             };
 
-            Value::Array(Array { value: output })
+            Value::Array(output)
+        }
+        (Value::Array(lhs), rhs @ (Value::Number(_) | Value::String(_))) => {
+            budget.allocate(lhs.len())?;
+
+            let output = lhs
+                .into_iter()
+                .map(|v| {
+                    budget.step()?;
+                    apply(op, v, rhs.clone(), budget)
+                })
+                .collect::<Result<_, _>>()?;
+
+            Value::Array(output)
+        }
+        (lhs @ (Value::Number(_) | Value::String(_)), Value::Array(rhs)) => {
+            budget.allocate(rhs.len())?;
+
+            let output = rhs
+                .into_iter()
+                .map(|v| {
+                    budget.step()?;
+                    apply(op, lhs.clone(), v, budget)
+                })
+                .collect::<Result<_, _>>()?;
+
+            Value::Array(output)
+        }
+        (Value::Map(lhs), Value::Map(rhs)) => {
+            let output = lhs
+                .into_entries()
+                .into_iter()
+                .filter_map(|(k, lhs)| {
+                    let rhs = rhs.entries().get(&k)?.clone();
+                    Some(
+                        budget
+                            .step()
+                            .and_then(|()| apply(op, lhs, rhs, budget))
+                            .map(|v| (k, v)),
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+
+            Value::Map(Map::new(output))
+        }
+        (Value::Map(lhs), rhs) => {
+            let output = lhs
+                .into_entries()
+                .into_iter()
+                .map(|(k, v)| {
+                    budget.step()?;
+                    Ok((k, apply(op, v, rhs.clone(), budget)?))
+                })
+                .collect::<Result<_, RuntimeError>>()?;
+
+            Value::Map(Map::new(output))
+        }
+        (lhs, Value::Map(rhs)) => {
+            let output = rhs
+                .into_entries()
+                .into_iter()
+                .map(|(k, v)| {
+                    budget.step()?;
+                    Ok((k, apply(op, lhs.clone(), v, budget)?))
+                })
+                .collect::<Result<_, RuntimeError>>()?;
+
+            Value::Map(Map::new(output))
         }
         (Value::Number(lhs), Value::Number(rhs)) => {
-            if op.modifier.contains(Modifier::Flip) {
-                operate(op.operator, rhs, lhs)
+            let (lhs, rhs) = if op.modifier.contains(Modifier::Flip) {
+                (rhs, lhs)
             } else {
-                operate(op.operator, lhs, rhs)
+                (lhs, rhs)
+            };
+
+            operate(op.operator, lhs, rhs)?
+        }
+        // Strings only support comparison.
+        (Value::String(lhs), Value::String(rhs)) if matches!(op.operator, Operator::Eq) => {
+            Value::Number(Number {
+                value: (lhs == rhs) as isize,
+            })
+        }
+        (lhs, rhs) => {
+            // Report the operand that is neither, the other may be fine.
+            let found = match lhs {
+                Value::Number(_) | Value::Array(_) => rhs.type_name(),
+                _ => lhs.type_name(),
+            };
+
+            return Err(TypeMismatchError::new("number or array", found).into());
+        }
+    })
+}
+
+fn apply_unary(op: Operation, val: Value, budget: &mut Budget) -> Result<Value, RuntimeError> {
+    match val {
+        Value::Array(arr) => {
+            if let Some(value) = reduce_flat(op, &arr, budget)? {
+                return Ok(value);
             }
+
+            let rest = arr
+                .len()
+                .checked_sub(1)
+                .ok_or(RuntimeError::EmptyReduction)?;
+            let mut first = arr.get(rest).unwrap();
+
+            for v in arr.iter().take(rest) {
+                budget.step()?;
+                first = apply(op, first, v, budget)?;
+            }
+
+            Ok(first)
         }
+        Value::Map(map) => apply_unary(
+            op,
+            Value::Array(map.into_entries().into_values().collect()),
+            budget,
+        ),
+        Value::Number(_) | Value::String(_) | Value::Function(_) => Ok(val),
     }
 }
 
-fn apply_unary(op: Operation, val: Value) -> Value {
-    match val {
-        Value::Array(mut arr) => {
-            let mut first = arr.value.pop().unwrap();
+/// `apply` for flat arrays, progressions and numbers, looping over their
+/// numbers directly. `None` if the operands need the general case. Only
+/// adding and subtracting progressions, or multiplying one by a number,
+/// keeps the result lazy, every other result is stored.
+fn apply_flat(
+    op: Operation,
+    lhs: &Value,
+    rhs: &Value,
+    budget: &mut Budget,
+) -> Result<Option<Value>, RuntimeError> {
+    if !op.operator.is_scalar() {
+        return Ok(None);
+    }
+
+    let f = &scalar(op);
+
+    let array = match (lhs, rhs) {
+        (Value::Array(lhs), Value::Array(rhs)) => {
+            let (Some(lhs_shape), Some(lhs_numbers), Some(rhs_shape), Some(rhs_numbers)) =
+                (lhs.shape(), lhs.numbers(), rhs.shape(), rhs.numbers())
+            else {
+                return Ok(None);
+            };
+
+            if op.modifier.contains(Modifier::Table) {
+                if lhs_shape.len() != 1 || rhs_shape.len() != 1 {
+                    return Ok(None);
+                }
+                let len = lhs.len().saturating_mul(rhs.len());
+                budget.allocate(len)?;
+
+                let mut data = reserve(len)?;
+                for l in lhs_numbers {
+                    for r in rhs_numbers.clone() {
+                        budget.read_number()?;
+                        data.push(f(l, r)?);
+                    }
+                }
+
+                Array::flat(vec![lhs.len(), rhs.len()], data)
+            } else if lhs_shape == rhs_shape || (lhs_shape.len() == 1 && rhs_shape.len() == 1) {
+                // Vectors of different lengths are zipped to the shorter one.
+                let shape = if lhs_shape == rhs_shape {
+                    lhs_shape.to_vec()
+                } else {
+                    vec![lhs.len().min(rhs.len())]
+                };
+                if let Some(array) =
+                    progression(op, lhs.as_progression(), rhs.as_progression(), shape[0])
+                {
+                    return Ok(Some(Value::Array(array)));
+                }
+                budget.allocate(nested_elements(&shape))?;
+
+                let mut data = reserve(lhs_numbers.len().min(rhs_numbers.len()))?;
+                for (l, r) in lhs_numbers.zip(rhs_numbers) {
+                    budget.read_number()?;
+                    data.push(f(l, r)?);
+                }
+
+                Array::flat(shape, data)
+            } else {
+                return Ok(None);
+            }
+        }
+        (Value::Array(lhs), Value::Number(rhs)) => {
+            let (Some(shape), Some(numbers)) = (lhs.shape(), lhs.numbers()) else {
+                return Ok(None);
+            };
+            if let Some(array) =
+                progression(op, lhs.as_progression(), Some((rhs.value, 0)), lhs.len())
+            {
+                return Ok(Some(Value::Array(array)));
+            }
+            budget.allocate(nested_elements(shape))?;
+
+            let mut data = reserve(numbers.len())?;
+            for l in numbers {
+                budget.read_number()?;
+                data.push(f(l, rhs.value)?);
+            }
+
+            Array::flat(shape.to_vec(), data)
+        }
+        (Value::Number(lhs), Value::Array(rhs)) => {
+            let (Some(shape), Some(numbers)) = (rhs.shape(), rhs.numbers()) else {
+                return Ok(None);
+            };
+            if let Some(array) =
+                progression(op, Some((lhs.value, 0)), rhs.as_progression(), rhs.len())
+            {
+                return Ok(Some(Value::Array(array)));
+            }
+            budget.allocate(nested_elements(shape))?;
 
-            for v in arr.value {
-                first = apply(op, first, v);
+            let mut data = reserve(numbers.len())?;
+            for r in numbers {
+                budget.read_number()?;
+                data.push(f(lhs.value, r)?);
             }
 
-            first
+            Array::flat(shape.to_vec(), data)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Value::Array(array)))
+}
+
+/// `op` on the first `len` elements of two progressions, given as their
+/// first element and step with numbers as a step of 0, if the result is a
+/// progression as well.
+fn progression(
+    op: Operation,
+    lhs: Option<(isize, isize)>,
+    rhs: Option<(isize, isize)>,
+    len: usize,
+) -> Option<Array> {
+    let (lhs, rhs) = (lhs?, rhs?);
+    let ((lhs_start, lhs_step), (rhs_start, rhs_step)) = if op.modifier.contains(Modifier::Flip) {
+        (rhs, lhs)
+    } else {
+        (lhs, rhs)
+    };
+
+    let (start, step) = match op.operator {
+        Operator::Add => (
+            lhs_start.checked_add(rhs_start)?,
+            lhs_step.checked_add(rhs_step)?,
+        ),
+        Operator::Sub => (
+            lhs_start.checked_sub(rhs_start)?,
+            lhs_step.checked_sub(rhs_step)?,
+        ),
+        Operator::Mul if rhs_step == 0 => (
+            lhs_start.checked_mul(rhs_start)?,
+            lhs_step.checked_mul(rhs_start)?,
+        ),
+        Operator::Mul if lhs_step == 0 => (
+            lhs_start.checked_mul(rhs_start)?,
+            lhs_start.checked_mul(rhs_step)?,
+        ),
+        _ => return None,
+    };
+
+    // The last element has to fit as well as the first.
+    isize::try_from(len.saturating_sub(1))
+        .ok()?
+        .checked_mul(step)?
+        .checked_add(start)?;
+
+    Some(Array::progression(start, step, len))
+}
+
+/// `apply_unary` for flat arrays and progressions, combining whole rows at a
+/// time.
+fn reduce_flat(
+    op: Operation,
+    arr: &Array,
+    budget: &mut Budget,
+) -> Result<Option<Value>, RuntimeError> {
+    let (Some(shape), Some(numbers)) = (arr.shape(), arr.numbers()) else {
+        return Ok(None);
+    };
+
+    let inner = &shape[1..];
+    let size = inner.iter().product::<usize>();
+
+    // An outer product of rows nests deeper than the rows themselves.
+    let table = op.modifier.contains(Modifier::Table) && !inner.is_empty();
+    if !op.operator.is_scalar() || table || numbers.len() == 0 {
+        return Ok(None);
+    }
+
+    if let (Operator::Add, Some(progression)) = (op.operator, arr.as_progression()) {
+        if let Some(value) = progression_sum(progression, arr.len()) {
+            return Ok(Some(Value::Number(Number { value })));
+        }
+    }
+
+    let f = scalar(op);
+
+    // Like the general case, the last row is combined with each of the
+    // others in order.
+    let rest = numbers.len() - size;
+    let mut acc: Vec<isize> = numbers.clone().skip(rest).collect();
+    let mut numbers = numbers.take(rest);
+
+    if inner.is_empty() {
+        let value = numbers.try_fold(acc[0], |a, b| {
+            budget.read_number()?;
+            f(a, b)
+        })?;
+        return Ok(Some(Value::Number(Number { value })));
+    }
+
+    for _ in 0..rest / size {
+        budget.allocate(nested_elements(inner))?;
+
+        for (a, b) in acc.iter_mut().zip(&mut numbers) {
+            budget.read_number()?;
+            *a = f(*a, b)?;
+        }
+    }
+
+    Ok(Some(Value::Array(Array::flat(inner.to_vec(), acc))))
+}
+
+/// The sum of the first `len` elements of a progression, if it fits.
+fn progression_sum((start, step): (isize, isize), len: usize) -> Option<isize> {
+    let (start, step, len) = (start as i128, step as i128, len as i128);
+    let steps = len.checked_mul(len - 1)? / 2;
+    let sum = len
+        .checked_mul(start)?
+        .checked_add(step.checked_mul(steps)?)?;
+
+    isize::try_from(sum).ok()
+}
+
+/// The operator of `op` on two numbers, with the operands swapped by `Flip`.
+fn scalar(op: Operation) -> impl Fn(isize, isize) -> Result<isize, RuntimeError> {
+    let flip = op.modifier.contains(Modifier::Flip);
+
+    move |lhs, rhs| {
+        if flip {
+            operate_scalar(op.operator, rhs, lhs)
+        } else {
+            operate_scalar(op.operator, lhs, rhs)
         }
-        Value::Number(_) => val,
     }
 }
+
+/// An empty buffer for `len` numbers, failing rather than aborting when
+/// there is no memory for them. Most operators on a range store their
+/// result, so this is where a large one runs out.
+fn reserve(len: usize) -> Result<Vec<isize>, RuntimeError> {
+    let mut data = vec![];
+    data.try_reserve_exact(len)
+        .map_err(|_| RuntimeError::OutOfMemory(len))?;
+
+    Ok(data)
+}
+
+/// The elements stored for `value` once it is a row of a new array, which
+/// are those of a short progression, see `STORED_ROW_LEN`.
+fn row_elements(value: &Value) -> usize {
+    match value {
+        Value::Array(array) if array.len() <= STORED_ROW_LEN => lazy_elements(value),
+        _ => 0,
+    }
+}
+
+/// The elements of `value` that are not stored yet because it is a
+/// progression, which are charged once something needs them as values.
+fn lazy_elements(value: &Value) -> usize {
+    match value {
+        Value::Array(array) if array.as_progression().is_some() => array.len(),
+        _ => 0,
+    }
+}
+
+/// `lazy_elements` of `value` and of every array nested in it.
+fn nested_lazy_elements(value: &Value) -> usize {
+    match value {
+        Value::Array(array) => match array.elements() {
+            Some(elements) => elements
+                .iter()
+                .map(nested_lazy_elements)
+                .fold(0, usize::saturating_add),
+            None => lazy_elements(value),
+        },
+        _ => 0,
+    }
+}
+
+/// The elements the general case accounts for when building an array of
+/// `shape` level by level.
+fn nested_elements(shape: &[usize]) -> usize {
+    shape
+        .iter()
+        .scan(1usize, |count, &len| {
+            *count = count.saturating_mul(len);
+            Some(*count)
+        })
+        .fold(0, usize::saturating_add)
+}
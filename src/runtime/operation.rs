@@ -1,6 +1,6 @@
 use crate::parser::Number;
 
-use super::{Array, Value};
+use super::{error::RuntimeError, Array, Value};
 
 use bitflags::bitflags;
 
@@ -12,13 +12,13 @@ bitflags! {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct Operation {
     pub operator: Operator,
     pub modifier: Modifier,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum Operator {
     Add,
     Sub,
@@ -28,10 +28,16 @@ pub enum Operator {
     Range,
     Eq,
     Or,
+    And,
 }
 
 impl Operator {
-    pub fn from_str(value: &str) -> Option<Self> {
+    /// Whether applying the operator to two numbers gives a number.
+    pub fn is_scalar(self) -> bool {
+        !matches!(self, Operator::Range)
+    }
+
+    pub fn from_symbol(value: &str) -> Option<Self> {
         Some(match value {
             "+" => Operator::Add,
             "-" => Operator::Sub,
@@ -41,43 +47,39 @@ impl Operator {
             ".." => Operator::Range,
             "==" => Operator::Eq,
             "||" => Operator::Or,
+            "&&" => Operator::And,
             _ => return None,
         })
     }
 }
 
-pub fn operate(op: Operator, lhs: Number, rhs: Number) -> Value {
-    match op {
-        Operator::Add => Value::Number(Number {
-            value: lhs.value + rhs.value,
-        }),
-        Operator::Sub => Value::Number(Number {
-            value: lhs.value - rhs.value,
-        }),
-        Operator::Div => Value::Number(Number {
-            value: lhs.value / rhs.value,
-        }),
-        Operator::Mul => Value::Number(Number {
-            value: lhs.value * rhs.value,
-        }),
-        Operator::Mod => Value::Number(Number {
-            value: lhs.value % rhs.value,
-        }),
-        Operator::Range => Value::Array(Array {
-            value: (lhs.value..=rhs.value)
-                .map(|n| Value::Number(Number { value: n }))
-                .collect(),
+pub fn operate(op: Operator, lhs: Number, rhs: Number) -> Result<Value, RuntimeError> {
+    Ok(match op {
+        Operator::Range => Value::Array(Array::range(lhs.value, rhs.value)),
+        _ => Value::Number(Number {
+            value: operate_scalar(op, lhs.value, rhs.value)?,
         }),
-        Operator::Eq => Value::Number(Number {
-            value: if lhs.value == rhs.value { 1 } else { 0 },
-        }),
-        Operator::Or => Value::Number(Number {
-            value: if lhs.value != 0 || rhs.value != 0 {
-                1
-            } else {
-                0
-            },
-        }),
-        _ => todo!(),
+    })
+}
+
+/// Applies an operator whose result is a number, which is every operator
+/// but `Range`. Results that do not fit are an error rather than wrapped.
+pub fn operate_scalar(op: Operator, lhs: isize, rhs: isize) -> Result<isize, RuntimeError> {
+    if matches!(op, Operator::Div | Operator::Mod) && rhs == 0 {
+        return Err(RuntimeError::DivisionByZero);
     }
+
+    let value = match op {
+        Operator::Add => lhs.checked_add(rhs),
+        Operator::Sub => lhs.checked_sub(rhs),
+        Operator::Div => lhs.checked_div(rhs),
+        Operator::Mul => lhs.checked_mul(rhs),
+        Operator::Mod => lhs.checked_rem(rhs),
+        Operator::Eq => Some((lhs == rhs) as isize),
+        Operator::Or => Some((lhs != 0 || rhs != 0) as isize),
+        Operator::And => Some((lhs != 0 && rhs != 0) as isize),
+        Operator::Range => unreachable!("ranges are arrays"),
+    };
+
+    value.ok_or(RuntimeError::Overflow)
 }
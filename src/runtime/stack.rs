@@ -0,0 +1,55 @@
+//! How much native stack the current thread has left. Lambda calls recurse
+//! on it, so a call that would leave less than `RED_ZONE` fails with an
+//! error instead of overflowing, whatever stack the host gave the thread.
+
+/// Room kept for the work done between two calls, such as builtins and
+/// operators recursing into nested arrays.
+const RED_ZONE: usize = 256 * 1024;
+
+thread_local! {
+    /// The address where the red zone starts, 0 if the stack bounds are
+    /// unknown.
+    static LIMIT: usize = lowest_address().map_or(0, |low| low + RED_ZONE);
+}
+
+/// Whether the stack of the current thread is nearly used up. The stack
+/// grows down on every supported target.
+pub fn exhausted() -> bool {
+    let here = 0u8;
+
+    LIMIT.with(|&limit| (&here as *const u8 as usize) < limit)
+}
+
+#[cfg(target_os = "linux")]
+fn lowest_address() -> Option<usize> {
+    use std::{mem::MaybeUninit, ptr};
+
+    unsafe {
+        let mut attr = MaybeUninit::<libc::pthread_attr_t>::uninit();
+        if libc::pthread_getattr_np(libc::pthread_self(), attr.as_mut_ptr()) != 0 {
+            return None;
+        }
+
+        let mut addr = ptr::null_mut();
+        let mut size = 0;
+        let found = libc::pthread_attr_getstack(attr.as_ptr(), &mut addr, &mut size) == 0;
+        libc::pthread_attr_destroy(attr.as_mut_ptr());
+
+        found.then_some(addr as usize)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn lowest_address() -> Option<usize> {
+    unsafe {
+        let thread = libc::pthread_self();
+        let top = libc::pthread_get_stackaddr_np(thread) as usize;
+
+        Some(top - libc::pthread_get_stacksize_np(thread))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn lowest_address() -> Option<usize> {
+    None
+}
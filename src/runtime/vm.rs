@@ -0,0 +1,185 @@
+//! Executes compiled chunks on the runtime's value stack.
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use crate::parser::Number;
+
+use super::{
+    apply, apply_unary,
+    compiler::{Chunk, Instruction},
+    error::RuntimeError,
+    function::{Closure, Env, Function},
+    operation::Operator,
+    row_elements, Array, Map, Runtime, Value,
+};
+
+impl Runtime {
+    /// Runs `chunk` with the arguments in `env` and returns the value it
+    /// leaves on the stack. The stack is restored to where it was on error.
+    pub(super) fn run(
+        &mut self,
+        chunk: &Chunk,
+        env: Option<&Rc<Env>>,
+    ) -> Result<Value, RuntimeError> {
+        let base = self.stack.len();
+
+        match self.execute(chunk, env) {
+            Ok(()) => Ok(self
+                .stack
+                .pop()
+                .expect("a chunk leaves its value on the stack")),
+            Err(e) => {
+                self.stack.truncate(base);
+                Err(e)
+            }
+        }
+    }
+
+    fn execute(&mut self, chunk: &Chunk, env: Option<&Rc<Env>>) -> Result<(), RuntimeError> {
+        let mut pc = 0;
+
+        while let Some(&instruction) = chunk.code.get(pc) {
+            pc += 1;
+
+            self.budget.step()?;
+
+            match instruction {
+                Instruction::Constant(i) => self.stack.push(chunk.constants[i].clone()),
+                Instruction::LoadVar(i) => {
+                    let value = self.get_var(chunk.names[i].clone())?;
+                    self.stack.push(value);
+                }
+                Instruction::LoadArg { depth, index } => {
+                    let value =
+                        argument(env, depth, index).ok_or(RuntimeError::UnboundArgument(index))?;
+                    self.stack.push(value);
+                }
+                Instruction::TakeArg(index) => {
+                    let value = take_argument(env, index)?;
+                    self.stack.push(value);
+                }
+                Instruction::StoreVar(i) => {
+                    let value = self.peek().clone();
+                    self.push_var(&chunk.names[i], value);
+                }
+                Instruction::Function(i) => {
+                    let name = &chunk.names[i];
+                    let function = match self.variables.get(name) {
+                        Some(Value::Function(function)) => function.clone(),
+                        _ => self.lookup_function(name)?,
+                    };
+                    self.stack.push(Value::Function(function));
+                }
+                Instruction::ArgFunction { depth, index, name } => {
+                    let function = match argument(env, depth, index) {
+                        Some(Value::Function(function)) => function,
+                        _ => self.lookup_function(&chunk.names[name])?,
+                    };
+                    self.stack.push(Value::Function(function));
+                }
+                Instruction::Closure(i) => {
+                    let closure = Closure {
+                        chunk: chunk.lambdas[i].clone(),
+                        env: env.cloned(),
+                    };
+                    self.stack.push(Value::Function(Function::Lambda(closure)));
+                }
+                Instruction::Define { name, lambda } => {
+                    let function = Function::Lambda(Closure {
+                        chunk: chunk.lambdas[lambda].clone(),
+                        env: env.cloned(),
+                    });
+                    self.functions
+                        .insert(chunk.names[name].clone(), function.clone());
+                    self.stack.push(Value::Function(function));
+                }
+                Instruction::Array(len) => {
+                    let value = self.stack.split_off(self.stack.len() - len);
+                    let rows = value.iter().map(row_elements).sum::<usize>();
+                    self.budget.allocate(len.saturating_add(rows))?;
+
+                    self.stack.push(Value::Array(Array::new(value)));
+                }
+                Instruction::Map(len) => {
+                    let mut entries = self.stack.split_off(self.stack.len() - 2 * len).into_iter();
+                    let mut value = BTreeMap::new();
+
+                    while let (Some(k), Some(v)) = (entries.next(), entries.next()) {
+                        value.insert(k, v);
+                    }
+                    self.stack.push(Value::Map(Map::new(value)));
+                }
+                Instruction::Binary(op) => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let value = apply(op, lhs, rhs, &mut self.budget)?;
+                    self.stack.push(value);
+                }
+                Instruction::Unary(op) => {
+                    let val = self.pop();
+                    let value = apply_unary(op, val, &mut self.budget)?;
+                    self.stack.push(value);
+                }
+                Instruction::Call => {
+                    let args = self.pop();
+                    let function = match self.pop() {
+                        Value::Function(function) => function,
+                        value => return Err(RuntimeError::NotCallable(value.type_name())),
+                    };
+                    let value = self.call(&function, args)?;
+                    self.stack.push(value);
+                }
+                Instruction::JumpIfFalse(target) => {
+                    if !self.pop().is_true()? {
+                        pc = target;
+                    }
+                }
+                Instruction::Jump(target) => pc = target,
+                Instruction::ShortCircuit(operator, target) => {
+                    // Scalar conditions short-circuit, arrays are combined
+                    // element-wise as with any other operator.
+                    if let Value::Number(number) = self.peek() {
+                        let result = match (operator, number.value != 0) {
+                            (Operator::Or, true) => Some(1),
+                            (Operator::And, false) => Some(0),
+                            _ => None,
+                        };
+
+                        if let Some(value) = result {
+                            *self.stack.last_mut().unwrap() = Value::Number(Number { value });
+                            pc = target;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("operands are pushed before use")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("operands are pushed before use")
+    }
+}
+
+fn argument(env: Option<&Rc<Env>>, depth: usize, index: usize) -> Option<Value> {
+    env?.ancestor(depth)?.args.borrow().get(index)
+}
+
+/// Moves an argument out, leaving a number in its place.
+fn take_argument(env: Option<&Rc<Env>>, index: usize) -> Result<Value, RuntimeError> {
+    let mut args = env
+        .ok_or(RuntimeError::UnboundArgument(index))?
+        .args
+        .borrow_mut();
+    let arg = args
+        .make_mut()?
+        .get_mut(index)
+        .ok_or(RuntimeError::UnboundArgument(index))?;
+
+    Ok(std::mem::replace(arg, Value::Number(Number { value: 0 })))
+}
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod test {
+    use vohtori::{Array, Number, Value};
+
+    use crate::input::{parse, Format};
+
+    fn number(value: isize) -> Value {
+        Value::Number(Number { value })
+    }
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_owned())
+    }
+
+    fn array(value: Vec<Value>) -> Value {
+        Value::Array(Array::new(value))
+    }
+
+    #[test]
+    fn non_integer_fields_become_strings() {
+        assert_eq!(
+            parse("1 a\n", Format::Ints),
+            array(vec![array(vec![number(1), string("a")])])
+        );
+    }
+
+    #[test]
+    fn csv_respects_quotes() {
+        assert_eq!(
+            parse("1,\"a,b\"\n", Format::Csv),
+            array(vec![array(vec![number(1), string("a,b")])])
+        );
+    }
+
+    #[test]
+    fn sections_split_on_blank_lines() {
+        assert_eq!(
+            parse("1\n\n\n2 3\n", Format::Sections),
+            array(vec![
+                array(vec![array(vec![number(1)])]),
+                array(vec![array(vec![number(2), number(3)])]),
+            ])
+        );
+    }
+
+    #[test]
+    fn extract_finds_signed_integers() {
+        assert_eq!(
+            parse("x=-3, y=4-5\n", Format::Extract),
+            array(vec![array(vec![number(-3), number(4), number(5)])])
+        );
+    }
+}
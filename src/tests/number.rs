@@ -1,5 +1,3 @@
-use crate::runtime::number::Number;
-
 macro_rules! number {
     (-, $($i:expr),*) => {
         Number {
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod test {
+    use vohtori::{Array, Number, Value};
+
+    use crate::output::{Boxed, Csv, Formatter, Human, Json};
+
+    fn format(formatter: impl Formatter, value: &Value) -> String {
+        let mut out = vec![];
+        formatter.write(value, &mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    fn matrix(rows: &[&[isize]]) -> Value {
+        Value::Array(
+            rows.iter()
+                .map(|row| {
+                    Value::Array(
+                        row.iter()
+                            .map(|&value| Value::Number(Number { value }))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn human_aligns_columns() {
+        assert_eq!(
+            format(Human, &matrix(&[&[1, 200], &[40, 5]])),
+            " 1 200\n40   5\n"
+        );
+    }
+
+    #[test]
+    fn csv_writes_rows() {
+        assert_eq!(format(Csv, &matrix(&[&[1, 2], &[3, 4]])), "1,2\n3,4\n");
+        assert_eq!(
+            format(Csv, &Value::String("a,\"b\"".to_owned())),
+            "\"a,\"\"b\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn json_escapes_strings() {
+        assert_eq!(
+            format(Json, &Value::String("a\"\n".to_owned())),
+            "\"a\\\"\\n\"\n"
+        );
+    }
+
+    #[test]
+    fn boxed_draws_nested_boxes() {
+        let value = Value::Array(Array::new(vec![
+            Value::Number(Number { value: 1 }),
+            matrix(&[&[2, 3]]),
+        ]));
+
+        assert_eq!(
+            format(Boxed::default(), &value),
+            "┌───────┐\n│1 ┌───┐│\n│  │2 3││\n│  └───┘│\n└───────┘\n"
+        );
+    }
+
+    #[test]
+    fn boxed_elides_long_arrays() {
+        let boxed = Boxed {
+            max_width: 10,
+            max_elements: 2,
+        };
+
+        assert_eq!(
+            format(boxed, &matrix(&[&[1, 2, 3]])),
+            "┌───────┐\n│1 2 ...│\n└───────┘\n"
+        );
+        assert_eq!(
+            format(
+                Boxed {
+                    max_width: 6,
+                    max_elements: 10
+                },
+                &matrix(&[&[1, 2, 3, 4]])
+            ),
+            "┌──...\n│1 ...\n└──...\n"
+        );
+    }
+
+    #[test]
+    fn boxed_elides_flat_matrices() {
+        let boxed = Boxed {
+            max_width: 20,
+            max_elements: 2,
+        };
+        let value = Value::Array(Array::flat(vec![3, 3], (1..=9).collect()));
+
+        assert_eq!(
+            format(boxed, &value),
+            "┌─────────┐\n│  1 2 ...│\n│  4 5 ...│\n│...      │\n└─────────┘\n"
+        );
+        assert_eq!(format(Human, &value), "1 2 3\n4 5 6\n7 8 9\n");
+    }
+}
@@ -0,0 +1,245 @@
+#[cfg(test)]
+mod test {
+    use std::io::{self, Read};
+
+    use vohtori::{Array, Map, Number, Runtime, Value};
+
+    use crate::repl::{
+        command::{shape, Command},
+        completion::{candidates, common_prefix, complete, Candidate},
+        editor::open_brackets,
+        terminal::{read_key, Key, Keys},
+    };
+
+    #[test]
+    fn brackets_outside_strings_are_counted() {
+        assert_eq!(open_brackets("f: [1 (2"), 2);
+        assert_eq!(open_brackets("{\"a\": [1]}"), 0);
+        assert_eq!(open_brackets("\"([{\" + ["), 1);
+        assert_eq!(open_brackets("1)"), -1);
+        assert_eq!(open_brackets(""), 0);
+    }
+
+    fn keys(mut input: impl Keys) -> Vec<Key> {
+        let mut keys = vec![];
+
+        while let Some(key) = read_key(&mut input).unwrap() {
+            keys.push(key);
+        }
+
+        keys
+    }
+
+    #[test]
+    fn keys_are_decoded() {
+        assert_eq!(
+            keys("aä\r\t\x7f\x01".as_bytes()),
+            [
+                Key::Char('a'),
+                Key::Char('ä'),
+                Key::Enter,
+                Key::Tab,
+                Key::Backspace,
+                Key::Ctrl('a')
+            ]
+        );
+        assert_eq!(
+            keys(b"\x1b[A\x1bOD\x1b[3~\x1b[1~".as_slice()),
+            [Key::Up, Key::Left, Key::Delete, Key::Home]
+        );
+    }
+
+    /// Bytes typed with a pause after the first `pause` of them.
+    struct Typed {
+        bytes: &'static [u8],
+        read: usize,
+        pause: usize,
+    }
+
+    impl Read for Typed {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = (&self.bytes[self.read..]).read(&mut buf[..1])?;
+            self.read += read;
+
+            Ok(read)
+        }
+    }
+
+    impl Keys for Typed {
+        fn pending(&mut self) -> bool {
+            self.read != self.pause && self.read < self.bytes.len()
+        }
+    }
+
+    #[test]
+    fn bare_escape_does_not_wait() {
+        let input = Typed {
+            bytes: b"\x1b[A",
+            read: 0,
+            pause: 1,
+        };
+
+        assert_eq!(keys(input), [Key::Unknown, Key::Char('['), Key::Char('A')]);
+        assert_eq!(keys(b"\x1b".as_slice()), [Key::Unknown]);
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert!(matches!(Command::parse(":vars"), Ok(Command::Vars)));
+        assert!(matches!(Command::parse("  :q "), Ok(Command::Quit)));
+        assert!(matches!(
+            Command::parse(":time  + 1..10 "),
+            Ok(Command::Time("+ 1..10"))
+        ));
+        assert!(matches!(
+            Command::parse(":load-workspace a b"),
+            Ok(Command::LoadWorkspace("a b"))
+        ));
+        assert!(matches!(Command::parse(":help"), Ok(Command::Help(None))));
+        assert!(matches!(
+            Command::parse(":help sum"),
+            Ok(Command::Help(Some("sum")))
+        ));
+    }
+
+    #[test]
+    fn invalid_commands_are_reported() {
+        assert!(matches!(
+            Command::parse(":ast"),
+            Err(e) if e == ":ast expects an expression"
+        ));
+        assert!(matches!(
+            Command::parse(":nope 1"),
+            Err(e) if e == "Unknown command :nope, see :help"
+        ));
+    }
+
+    fn number(value: isize) -> Value {
+        Value::Number(Number { value })
+    }
+
+    fn nested(values: Vec<Value>) -> Value {
+        Value::Array(Array::nested(values))
+    }
+
+    #[test]
+    fn shapes_describe_values() {
+        assert_eq!(shape(&number(1)), "number");
+        assert_eq!(shape(&Value::String("a".to_owned())), "string");
+        assert_eq!(shape(&Value::Map(Map::default())), "map of 0");
+
+        assert_eq!(
+            shape(&Value::Array(Array::flat(vec![2, 3], vec![0; 6]))),
+            "array 2×3"
+        );
+        assert_eq!(
+            shape(&nested(vec![
+                nested(vec![number(1), number(2)]),
+                nested(vec![number(3), number(4)]),
+            ])),
+            "array 2×2"
+        );
+        assert_eq!(
+            shape(&nested(vec![nested(vec![number(1)]), number(2)])),
+            "ragged array of 2"
+        );
+        assert_eq!(
+            shape(&nested(vec![nested(vec![]), nested(vec![number(1)])])),
+            "ragged array of 2"
+        );
+    }
+
+    #[test]
+    fn empty_arrays_have_zero_length() {
+        assert_eq!(shape(&Value::Array(Array::default())), "array 0");
+        assert_eq!(shape(&nested(vec![])), "array 0");
+        assert_eq!(
+            shape(&nested(vec![nested(vec![]), nested(vec![])])),
+            "array 2×0"
+        );
+    }
+
+    fn candidate(name: &str) -> Candidate {
+        Candidate {
+            name: name.to_owned(),
+            signature: String::new(),
+        }
+    }
+
+    fn names(candidates: &[&Candidate]) -> Vec<String> {
+        candidates.iter().map(|c| c.name.clone()).collect()
+    }
+
+    #[test]
+    fn words_complete_to_sorted_candidates() {
+        let candidates = [
+            candidate("values"),
+            candidate("vars"),
+            candidate("var_a"),
+            candidate("vars"),
+            candidate(":vars"),
+        ];
+        let line: Vec<char> = "1 + va".chars().collect();
+
+        let (start, matches) = complete(&candidates, &line);
+        assert_eq!(start, 4);
+        assert_eq!(names(&matches), ["values", "var_a", "vars"]);
+
+        let line: Vec<char> = "1 +".chars().collect();
+        assert!(complete(&candidates, &line).1.is_empty());
+    }
+
+    #[test]
+    fn commands_only_complete_at_the_start() {
+        let candidates = [candidate(":load"), candidate(":load-workspace")];
+
+        let line: Vec<char> = " :load-w".chars().collect();
+        let (start, matches) = complete(&candidates, &line);
+        assert_eq!(start, 1);
+        assert_eq!(names(&matches), [":load-workspace"]);
+
+        let line: Vec<char> = ":load :lo".chars().collect();
+        assert!(complete(&candidates, &line).1.is_empty());
+    }
+
+    #[test]
+    fn common_prefix_of_matches() {
+        let (a, b, c) = (candidate("scan"), candidate("scale"), candidate("äb"));
+
+        assert_eq!(common_prefix(&[&a, &b]), "sca");
+        assert_eq!(common_prefix(&[&a]), "scan");
+        assert_eq!(common_prefix(&[&a, &c]), "");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn unique_matches_show_their_signature() {
+        let mut runtime = Runtime::new();
+        runtime.push_var("xs", Value::Array(Array::flat(vec![3], vec![1, 2, 3])));
+        runtime.push_lazy_var("lazy", || Ok(number(1)));
+        runtime.register_fn("scale", 2, |args| Ok(args[0].clone()));
+        vohtori::eval_str(
+            &mut runtime,
+            "fn inc n: n + 1\nfn add a b: a + b\nfn first: $0",
+        )
+        .unwrap();
+
+        let candidates = candidates(&runtime);
+        let signature = |line: &str| {
+            let chars: Vec<char> = line.chars().collect();
+            match complete(&candidates, &chars).1.as_slice() {
+                [candidate] => candidate.signature.clone(),
+                matches => panic!("{} matches for {}", matches.len(), line),
+            }
+        };
+
+        assert_eq!(signature("x"), "xs: array 3");
+        assert_eq!(signature("laz"), "lazy: not read yet");
+        assert_eq!(signature("ta"), "take: [array n]");
+        assert_eq!(signature("scale"), "scale: [value value]");
+        assert_eq!(signature("inc"), "inc: value");
+        assert_eq!(signature("ad"), "add: [value value]");
+        assert_eq!(signature("fir"), "first: arguments");
+        assert_eq!(signature(":res"), ":reset");
+    }
+}
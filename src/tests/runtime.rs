@@ -0,0 +1,740 @@
+#[cfg(test)]
+mod test {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    use crate::{
+        eval_str,
+        lexer::TokenType,
+        parser::{
+            error::{InvalidTokenTypeError, MissingTokenError, ParserError},
+            Number, Parser,
+        },
+        runtime::{
+            error::RuntimeError,
+            limits::{Limit, Limits},
+            Array, Map, Runtime, Value, MAX_DEPTH,
+        },
+        Error,
+    };
+
+    fn try_eval(source: &str) -> Result<Value, RuntimeError> {
+        let mut parser = Parser::new(source.as_bytes());
+        let mut runtime = Runtime::new();
+        let mut value = None;
+
+        while let Some(expr) = parser.parse_expr() {
+            value = Some(runtime.eval_expr(&expr.unwrap())?);
+        }
+
+        Ok(value.unwrap())
+    }
+
+    fn eval(source: &str) -> Value {
+        try_eval(source).unwrap()
+    }
+
+    fn number(value: isize) -> Value {
+        Value::Number(Number { value })
+    }
+
+    fn array(values: &[isize]) -> Value {
+        Value::Array(values.iter().map(|&value| number(value)).collect())
+    }
+
+    #[test]
+    fn fold_and_scan_pass_accumulator_first() {
+        assert_eq!(eval("fold: [[1 2 3] 10 $:($0 - $1)]"), number(4));
+        assert_eq!(eval("scan: [[1 2 3] 0 $:($0 + $1)]"), array(&[1, 3, 6]));
+    }
+
+    #[test]
+    fn iterate_includes_initial_value() {
+        assert_eq!(eval("iterate: [1 3 $:($0 * 3)]"), array(&[1, 3, 9, 27]));
+    }
+
+    #[test]
+    fn fixpoint_stops_when_unchanged() {
+        assert_eq!(eval("fixpoint: [[8 3] $:($0 / 2)]"), array(&[0, 0]));
+    }
+
+    #[test]
+    fn functions_are_values() {
+        assert_eq!(
+            eval("double = $:($0 * 2)\neach: [[1 2] double]"),
+            array(&[2, 4])
+        );
+        assert_eq!(eval("f = $idx\nf: [1 0 1]"), array(&[0, 0, 2]));
+        assert_eq!(
+            eval("make = $:($:($0 + 1))\ninc = make: 0\ninc: 5"),
+            number(6)
+        );
+    }
+
+    #[test]
+    fn closures_read_enclosing_arguments() {
+        assert_eq!(
+            eval("adder = $:($:($0 + $$0))\nadd = adder: 5\nadd: 10"),
+            number(15)
+        );
+        assert_eq!(
+            eval("each: [[1 2] $:(each: [[10] $:($0 + $$0)])]"),
+            Value::Array(Array::new(vec![array(&[11]), array(&[12])]))
+        );
+    }
+
+    #[test]
+    fn expressions_can_be_evaluated_again() {
+        let mut parser = Parser::new("x = x + 1".as_bytes());
+        let expr = parser.parse_expr().unwrap().unwrap();
+
+        let mut runtime = Runtime::new();
+        runtime.push_var("x", number(0));
+        for _ in 0..3 {
+            runtime.eval_expr(&expr).unwrap();
+        }
+
+        assert_eq!(eval_str(&mut runtime, "x").unwrap(), number(3));
+    }
+
+    #[test]
+    fn shared_arrays_are_copied_on_write() {
+        assert_eq!(eval("xs = [1 0 [2]]\nys = idx: xs\nxs"), eval("[1 0 [2]]"));
+
+        let mut shared = Array::new(vec![number(1)]);
+        let copy = shared.clone();
+        shared.make_mut().unwrap().push(number(2));
+
+        assert_eq!(Value::Array(copy), array(&[1]));
+        assert_eq!(shared.len(), 2);
+    }
+
+    #[test]
+    fn reading_and_passing_arrays_shares_them() {
+        for original in [
+            Array::new(vec![number(1), Value::String("a".to_owned())]),
+            Array::flat(vec![2, 2], vec![1, 2, 3, 4]),
+        ] {
+            let mut runtime = Runtime::new();
+            runtime.push_var("xs", Value::Array(original.clone()));
+
+            for source in ["xs", "ys = xs\nys", "fn id x: x\nid: xs", "$:($0): [xs]"] {
+                let Value::Array(result) = eval_str(&mut runtime, source).unwrap() else {
+                    panic!("{source} did not return an array");
+                };
+
+                assert!(result.shares_storage(&original), "{source} copied");
+            }
+
+            let Value::Array(result) = eval_str(&mut runtime, "xs").unwrap() else {
+                unreachable!();
+            };
+            let mut modified = result.clone();
+            modified.make_mut().unwrap().push(number(5));
+            assert!(!modified.shares_storage(&original));
+            assert!(result.shares_storage(&original));
+        }
+    }
+
+    #[test]
+    fn reading_and_passing_maps_shares_them() {
+        let original = Map::new([(number(1), array(&[2, 3]))].into_iter().collect());
+        let mut runtime = Runtime::new();
+        runtime.push_var("m", Value::Map(original.clone()));
+
+        for source in ["m", "n = m\nn", "fn id x: x\nid: m", "$:($0): [m]", "[m 1]"] {
+            let result = match eval_str(&mut runtime, source).unwrap() {
+                Value::Array(array) => array.get(0).unwrap(),
+                value => value,
+            };
+            let Value::Map(result) = result else {
+                panic!("{source} did not return a map");
+            };
+
+            assert!(result.shares_storage(&original), "{source} copied");
+        }
+
+        let Value::Map(mut modified) = eval_str(&mut runtime, "m").unwrap() else {
+            unreachable!();
+        };
+        modified.make_mut().insert(number(4), number(5));
+        assert!(!modified.shares_storage(&original));
+        assert_eq!(eval_str(&mut runtime, "keys: m").unwrap(), array(&[1]));
+    }
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_owned())
+    }
+
+    #[test]
+    fn maps_are_built_and_read() {
+        assert_eq!(eval("get: [{\"a\": 1 \"b\": [2]} \"b\"]"), array(&[2]));
+        assert_eq!(eval("keys: {2: \"x\" 1: \"y\"}"), array(&[1, 2]));
+        assert_eq!(
+            eval("values: {2: \"x\" 1: \"y\"}"),
+            Value::Array(Array::new(vec![string("y"), string("x")]))
+        );
+        assert_eq!(eval("{}"), Value::Map(Map::default()));
+        assert!(matches!(
+            try_eval("get: [{\"a\": 1} \"b\"]"),
+            Err(RuntimeError::KeyNotFound(key)) if key == "\"b\""
+        ));
+    }
+
+    #[test]
+    fn insert_leaves_the_original_map() {
+        assert_eq!(
+            eval("m = {1: 2}\nn = insert: [m 3 4]\n[(keys: m) (keys: n)]"),
+            eval("[[1] [1 3]]")
+        );
+        assert_eq!(eval("get: [(insert: [{1: 2} 1 5]) 1]"), number(5));
+        assert_eq!(
+            eval("m = fold: [(1..1000) {} $:(insert: [$0 ($1 % 10) $1])]\n+ values: m"),
+            number(9955)
+        );
+    }
+
+    #[test]
+    fn group_by_collects_elements_in_order() {
+        assert_eq!(
+            eval("group_by: [[1 2 3 4 5] $:($0 % 2)]"),
+            eval("{0: [2 4] 1: [1 3 5]}")
+        );
+        assert_eq!(
+            eval("fn first s: s\ngroup_by: [[\"b\" \"a\" \"b\"] $first]"),
+            eval("{\"a\": [\"a\"] \"b\": [\"b\" \"b\"]}")
+        );
+    }
+
+    #[test]
+    fn maps_print_in_key_order() {
+        assert_eq!(
+            eval("{\"b\": [1 2] \"a\": {1: 2}}").to_string(),
+            "{\"a\": {1: 2} \"b\": [1 2]}"
+        );
+        assert_eq!(eval("{1: 2} + {1: 10 2: 5}").to_string(), "{1: 12}");
+    }
+
+    /// Evaluates `source` with `v` and `m` bound to a vector and a matrix,
+    /// either flat or nested.
+    fn eval_with_arrays(flat: bool, source: &str) -> Value {
+        let pack = |values: Vec<Value>| {
+            if flat {
+                Array::new(values)
+            } else {
+                Array::nested(values)
+            }
+        };
+        let vector = |values: &[isize]| pack(values.iter().map(|&v| number(v)).collect());
+
+        let mut runtime = Runtime::new();
+        runtime.push_var("v", Value::Array(vector(&[3, 1, 2])));
+        runtime.push_var(
+            "m",
+            Value::Array(pack(vec![
+                Value::Array(vector(&[1, 2])),
+                Value::Array(vector(&[3, 4])),
+                Value::Array(vector(&[5, 6])),
+            ])),
+        );
+
+        eval_str(&mut runtime, source).unwrap()
+    }
+
+    #[test]
+    fn flat_arrays_behave_like_nested_ones() {
+        assert!(matches!(eval("[1 2]"), Value::Array(a) if a.shape() == Some(&[2])));
+        assert!(matches!(eval("[[1] [2 3]]"), Value::Array(a) if a.shape().is_none()));
+
+        for source in [
+            "v + 1",
+            "10 -:| v",
+            "v * [1 2 3 4]",
+            "v *:* v",
+            "m % 4",
+            "m == m",
+            "+ v",
+            "- m",
+            "-:| m",
+            "m *:* v",
+            "+ m *:* v",
+            "v .. 4",
+            "(1..3) - v",
+            "v *:* 0..2",
+            "take: [m 2]",
+        ] {
+            assert_eq!(
+                eval_with_arrays(true, source),
+                eval_with_arrays(false, source),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn ranges_are_not_stored() {
+        assert_eq!(eval("1..4"), array(&[1, 2, 3, 4]));
+        assert_eq!(eval("+ 1..1000000000"), number(500000000500000000));
+        assert_eq!(
+            eval("take: [((1..1000000000000) * 3 - 1) 3]"),
+            array(&[2, 4, 6])
+        );
+        assert!(
+            matches!(eval("10 -:| 1..5"), Value::Array(a) if a.as_progression() == Some((-9, 1)))
+        );
+        assert!(matches!(eval("[(1..2) (3..4)]"), Value::Array(a) if a.shape() == Some(&[2, 2])));
+        assert!(matches!(eval("[(1..5000) (1..5000)]"), Value::Array(a) if a.shape().is_none()));
+        assert_eq!(eval("+ (1..1000) % 7"), number(3003));
+        assert_eq!(
+            try_eval("+ (1..4000000000000000000) % 7"),
+            Err(RuntimeError::OutOfMemory(4000000000000000000))
+        );
+        assert_eq!(eval("fold: [(1..100) 0 $:($0 + $1)]"), number(5050));
+
+        // Storing the elements of a huge range fails cleanly without limits.
+        for source in [
+            "idx: 1..4000000000000000000",
+            "$:($0): 1..4000000000000000000",
+            "idx: 1..1000000000000",
+            "group_by: [(1..1000000000000) $:($0)]",
+        ] {
+            assert!(
+                matches!(try_eval(source), Err(RuntimeError::OutOfMemory(_))),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn named_functions_take_parameters() {
+        assert_eq!(eval("fn add a b: a + b\nadd: [2 3]"), number(5));
+        assert_eq!(eval("fn total xs: + xs\ntotal: [1 2 3]"), number(6));
+        assert_eq!(eval("fn total xs: + xs\nxs = [4 5]\ntotal: xs"), number(9));
+        assert_eq!(
+            eval("fn first xs: take: [xs 1]\nfirst: [[1 2] [3 4]]"),
+            eval("[[1 2]]")
+        );
+        assert_eq!(
+            eval("fn total xs: + xs\neach: [[[1 2] [3 4]] $total]"),
+            array(&[3, 7])
+        );
+        assert_eq!(
+            eval("fn twice f x: f: f: x\ntwice: [$:($0 * 3) 2]"),
+            number(18)
+        );
+    }
+
+    #[test]
+    fn unbounded_recursion_is_an_error() {
+        std::thread::Builder::new()
+            .stack_size(crate::STACK_SIZE)
+            .spawn(|| {
+                assert_eq!(
+                    try_eval("fn down n: down: n - 1\ndown: 5"),
+                    Err(RuntimeError::RecursionLimit(MAX_DEPTH))
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn recursion_stops_before_the_stack_overflows() {
+        let recurse = || {
+            for source in [
+                "fn down n: down: n - 1\ndown: 5",
+                "fn down n: each: [[n] $:(down: $0 - 1)]\ndown: 5",
+            ] {
+                assert!(matches!(
+                    try_eval(source),
+                    Err(RuntimeError::RecursionLimit(depth)) if depth < MAX_DEPTH
+                ));
+            }
+        };
+
+        // Neither the test thread nor this one has room for `MAX_DEPTH`
+        // calls.
+        recurse();
+        std::thread::Builder::new()
+            .stack_size(512 * 1024)
+            .spawn(recurse)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn conditionals_terminate_recursion() {
+        assert_eq!(
+            eval("fn fact n: if n == 0 then 1 else n * fact: n - 1\nfact: 5"),
+            number(120)
+        );
+    }
+
+    #[test]
+    fn logical_operators_short_circuit_on_scalars() {
+        assert_eq!(eval("1 || undefined"), number(1));
+        assert_eq!(eval("0 && undefined"), number(0));
+        assert_eq!(eval("[1 0 1] && [0 1 1]"), array(&[0, 0, 1]));
+    }
+
+    #[test]
+    fn eval_str_stops_at_the_first_error() {
+        let mut runtime = Runtime::new();
+
+        assert!(matches!(
+            eval_str(&mut runtime, "x = 1\n)"),
+            Err(Error::Parser(_))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "y = 2\nundefined\nz = 3"),
+            Err(Error::Runtime(RuntimeError::UndefinedVariable(_)))
+        ));
+        assert_eq!(eval_str(&mut runtime, "x + y").unwrap(), number(3));
+        assert!(eval_str(&mut runtime, "z").is_err());
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        let mut runtime = Runtime::new();
+
+        assert!(matches!(
+            eval_str(&mut runtime, "a, b"),
+            Err(Error::Parser(ParserError::InvalidCharacter(',')))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "99999999999999999999"),
+            Err(Error::Parser(ParserError::InvalidNumber(_)))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "1 +:"),
+            Err(Error::Parser(_))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "1 / 0"),
+            Err(Error::Runtime(RuntimeError::DivisionByZero))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "[1 2] % 0"),
+            Err(Error::Runtime(RuntimeError::DivisionByZero))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "9223372036854775807 + 1"),
+            Err(Error::Runtime(RuntimeError::Overflow))
+        ));
+    }
+
+    #[test]
+    fn type_errors_name_the_wrong_operand() {
+        let found = |source: &str| match try_eval(source) {
+            Err(RuntimeError::TypeMismatch(e)) => e.found,
+            result => panic!("{} gave {:?}", source, result),
+        };
+
+        assert_eq!(found("[1 2] + $idx"), "function");
+        assert_eq!(found("$idx + [1 2]"), "function");
+        assert_eq!(found("1 + \"a\""), "string");
+        assert_eq!(found("[1 \"a\"] + 2"), "string");
+    }
+
+    #[test]
+    fn parser_errors_are_displayed() {
+        let missing = ParserError::MissingToken(MissingTokenError::new(vec![
+            TokenType::Identifier,
+            TokenType::Operator,
+        ]));
+        assert_eq!(missing.to_string(), "Missing one of [Identifier, Operator]");
+
+        let invalid = ParserError::InvalidTokenType(InvalidTokenTypeError::new(
+            vec![TokenType::Identifier],
+            TokenType::SyntaxToken,
+        ));
+        assert_eq!(
+            invalid.to_string(),
+            "Expected one of [Identifier], found SyntaxToken"
+        );
+    }
+
+    #[test]
+    fn native_functions_are_called_like_builtins() {
+        let mut runtime = Runtime::new();
+        runtime.register_fn("double", 1, |mut args| {
+            let values = Vec::<Vec<i64>>::try_from(args.remove(0))?;
+            let doubled: Vec<Vec<i64>> = values
+                .into_iter()
+                .map(|row| row.into_iter().map(|v| v * 2).collect())
+                .collect();
+
+            Ok(doubled.into())
+        });
+        runtime.register_fn("square", 1, |mut args| {
+            let value = i64::try_from(args.remove(0))?;
+
+            Ok((value * value).into())
+        });
+        runtime.register_fn("greet", 2, |args| {
+            let name = String::try_from(args[0].clone())?;
+            let times = i64::try_from(args[1].clone())?;
+
+            Ok(vec![format!("hi {}", name); times as usize].into())
+        });
+
+        assert_eq!(
+            eval_str(&mut runtime, "double: [[1 2] [3 4]]").unwrap(),
+            Value::from(vec![vec![2i64, 4], vec![6, 8]])
+        );
+        assert_eq!(
+            eval_str(&mut runtime, "greet: [\"a\" 2]").unwrap(),
+            Value::from(vec!["hi a", "hi a"])
+        );
+        assert_eq!(
+            eval_str(&mut runtime, "each: [[2 3] $square]").unwrap(),
+            Value::from(vec![4i64, 9])
+        );
+        assert!(matches!(
+            eval_str(&mut runtime, "greet: [\"a\" 1 2]"),
+            Err(Error::Runtime(RuntimeError::ArgumentCount(_)))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "double: [1 2]"),
+            Err(Error::Runtime(RuntimeError::TypeMismatch(_)))
+        ));
+    }
+
+    #[test]
+    fn reset_keeps_native_functions() {
+        let mut runtime = Runtime::new();
+        runtime.register_fn("square", 1, |mut args| {
+            let value = i64::try_from(args.remove(0))?;
+
+            Ok((value * value).into())
+        });
+        eval_str(&mut runtime, "x = 2\nfn inc n: n + 1").unwrap();
+
+        runtime.reset();
+
+        assert_eq!(
+            eval_str(&mut runtime, "square: 3").unwrap(),
+            Value::from(9i64)
+        );
+        assert!(matches!(
+            eval_str(&mut runtime, "inc: 1"),
+            Err(Error::Runtime(RuntimeError::UndefinedFunction(_)))
+        ));
+        assert!(matches!(
+            eval_str(&mut runtime, "x"),
+            Err(Error::Runtime(RuntimeError::UndefinedVariable(_)))
+        ));
+    }
+
+    fn eval_limited(limits: Limits, source: &str) -> Result<Value, Error> {
+        let mut runtime = Runtime::new();
+        runtime.set_limits(limits);
+
+        eval_str(&mut runtime, source)
+    }
+
+    #[test]
+    fn limits_abort_evaluation() {
+        let elements = Limits {
+            max_elements: Some(1000),
+            ..Default::default()
+        };
+        assert!(matches!(
+            eval_limited(elements.clone(), "(1..1000000000) % 7"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(
+                Limit::Elements(1000)
+            )))
+        ));
+        assert!(matches!(
+            eval_limited(elements.clone(), "[(1..600) (1..600)]"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(
+                Limit::Elements(1000)
+            )))
+        ));
+        assert!(matches!(
+            eval_limited(elements.clone(), "$:($0): 1..1000000000"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(
+                Limit::Elements(1000)
+            )))
+        ));
+        assert!(matches!(
+            eval_limited(elements.clone(), "(1..100) *:* (1..100)"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(
+                Limit::Elements(1000)
+            )))
+        ));
+        // Ranges are only charged for once their elements are stored.
+        for source in [
+            "+ 1..100000",
+            "take: [(1..1000000000000) 3]",
+            "(1..1000000000) * 2 - 1",
+            "[1 [2]] + 1..1000000000",
+        ] {
+            assert!(eval_limited(elements.clone(), source).is_ok(), "{}", source);
+        }
+
+        let steps = Limits {
+            max_steps: Some(10_000),
+            ..Default::default()
+        };
+        assert!(matches!(
+            eval_limited(steps, "until: [0 $:(0) $:($0 + 1)]"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(Limit::Steps(
+                10_000
+            ))))
+        ));
+
+        let timeout = Limits {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            eval_limited(timeout, "until: [0 $:(0) $:($0 + 1)]"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(Limit::Timeout(
+                _
+            ))))
+        ));
+
+        let depth = Limits {
+            max_depth: 10,
+            ..Default::default()
+        };
+        assert!(matches!(
+            eval_limited(
+                depth,
+                "fn down n: if n == 0 then 0 else down: n - 1\ndown: 20"
+            ),
+            Err(Error::Runtime(RuntimeError::RecursionLimit(10)))
+        ));
+    }
+
+    #[test]
+    fn limits_stop_loops_over_ranges() {
+        let timeout = Limits {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            eval_limited(timeout, "- 1..3000000000"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(Limit::Timeout(
+                _
+            ))))
+        ));
+
+        let mut runtime = Runtime::new();
+        let flag = runtime.interrupt_flag();
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            flag.store(true, Ordering::Relaxed);
+        });
+
+        assert!(matches!(
+            eval_str(&mut runtime, "- 1..3000000000"),
+            Err(Error::Runtime(RuntimeError::Interrupted))
+        ));
+        interrupter.join().unwrap();
+    }
+
+    #[test]
+    fn limits_apply_to_each_evaluation() {
+        let mut runtime = Runtime::new();
+        runtime.set_limits(Limits {
+            max_elements: Some(100),
+            ..Default::default()
+        });
+
+        for _ in 0..10 {
+            assert!(eval_str(&mut runtime, "xs = 1..50").is_ok());
+        }
+    }
+
+    #[test]
+    fn limits_cover_every_line_of_a_source() {
+        let elements = Limits {
+            max_elements: Some(100),
+            ..Default::default()
+        };
+        let line = format!("xs = [{}]\n", "1 ".repeat(60));
+        assert!(eval_limited(elements.clone(), &line).is_ok());
+        assert!(matches!(
+            eval_limited(elements, &line.repeat(2)),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(
+                Limit::Elements(100)
+            )))
+        ));
+
+        let steps = Limits {
+            max_steps: Some(100),
+            ..Default::default()
+        };
+        let line = "x = [1 2 3] + [4 5 6]\n";
+        assert!(eval_limited(steps.clone(), line).is_ok());
+        assert!(matches!(
+            eval_limited(steps.clone(), &line.repeat(50)),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(Limit::Steps(
+                100
+            ))))
+        ));
+        // A single operator on ragged arrays loops over their elements.
+        assert!(matches!(
+            eval_limited(steps, "[[1] [2 3]] *:* 1..2000"),
+            Err(Error::Runtime(RuntimeError::LimitExceeded(Limit::Steps(
+                100
+            ))))
+        ));
+    }
+
+    #[test]
+    fn interrupts_leave_the_session_intact() {
+        let mut runtime = Runtime::new();
+        eval_str(&mut runtime, "x = 5").unwrap();
+
+        let flag = runtime.interrupt_flag();
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            flag.store(true, Ordering::Relaxed);
+        });
+
+        assert!(matches!(
+            eval_str(&mut runtime, "until: [0 $:(0) $:($0 + 1)]"),
+            Err(Error::Runtime(RuntimeError::Interrupted))
+        ));
+        interrupter.join().unwrap();
+
+        assert_eq!(eval_str(&mut runtime, "x").unwrap(), number(5));
+    }
+
+    #[test]
+    fn interrupts_between_evaluations_are_kept() {
+        let mut runtime = Runtime::new();
+        runtime.interrupt_flag().store(true, Ordering::Relaxed);
+
+        assert!(matches!(
+            eval_str(&mut runtime, "x = 1\ny = 2"),
+            Err(Error::Runtime(RuntimeError::Interrupted))
+        ));
+        assert!(eval_str(&mut runtime, "x").is_err());
+        assert_eq!(eval_str(&mut runtime, "x = 1").unwrap(), number(1));
+    }
+
+    #[test]
+    fn interrupts_stop_loops_over_elements() {
+        let mut runtime = Runtime::new();
+        let values = (0..1_000_000).map(number).collect();
+        runtime.push_var("xs", Value::Array(Array::nested(values)));
+
+        let flag = runtime.interrupt_flag();
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            flag.store(true, Ordering::Relaxed);
+        });
+
+        assert!(matches!(
+            eval_str(&mut runtime, "+ xs"),
+            Err(Error::Runtime(RuntimeError::Interrupted))
+        ));
+        interrupter.join().unwrap();
+    }
+}
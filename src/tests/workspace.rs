@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod test {
+    use vohtori::{Parser, Runtime, Value};
+
+    use crate::workspace::{load, save, WorkspaceError};
+
+    fn run(runtime: &mut Runtime, source: &str) {
+        let mut parser = Parser::new(source.as_bytes());
+
+        while let Some(expr) = parser.parse_expr() {
+            runtime.eval_expr(&expr.unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn variables_survive_a_round_trip() {
+        let mut runtime = Runtime::new();
+        run(
+            &mut runtime,
+            "xs = [1 [-2 3] \"a b\" \"\"]\nm = {\"k\": [4] 5: $fold}\nf = $:($0 + 1)",
+        );
+
+        let mut out = vec![];
+        let skipped = save(&runtime, &mut out).unwrap();
+        assert_eq!(skipped, ["f"]);
+
+        let mut restored = Runtime::new();
+        assert_eq!(load(&mut restored, out.as_slice()).unwrap(), 2);
+
+        let mut variables: Vec<_> = restored.variables().collect();
+        variables.sort_by_key(|&(name, _)| name);
+        let mut expected: Vec<_> = runtime
+            .variables()
+            .filter(|&(name, _)| name != "f")
+            .collect();
+        expected.sort_by_key(|&(name, _)| name);
+
+        assert_eq!(variables, expected);
+    }
+
+    #[test]
+    fn ranges_are_saved_without_their_elements() {
+        let mut runtime = Runtime::new();
+        run(&mut runtime, "xs = 1..1000000000");
+
+        let mut out = vec![];
+        save(&runtime, &mut out).unwrap();
+        let text = String::from_utf8(out.clone()).unwrap();
+        assert!(text.lines().any(|line| line == "xs r1,1,1000000000"));
+
+        let mut restored = Runtime::new();
+        assert_eq!(load(&mut restored, out.as_slice()).unwrap(), 1);
+        let (name, value) = restored.variables().next().unwrap();
+        assert_eq!(name, "xs");
+        // Comparing the elements one by one would take a while.
+        let Some(Value::Array(xs)) = value else {
+            panic!("expected an array, found {value:?}");
+        };
+        assert_eq!((xs.as_progression(), xs.len()), (Some((1, 1)), 1000000000));
+    }
+
+    #[test]
+    fn older_versions_are_read() {
+        let mut runtime = Runtime::new();
+
+        assert_eq!(
+            load(
+                &mut runtime,
+                "vohtori-workspace 1\nxs a2 n1 n2\n".as_bytes()
+            )
+            .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        let mut runtime = Runtime::new();
+
+        assert!(matches!(
+            load(&mut runtime, "vohtori-workspace 3\n".as_bytes()),
+            Err(WorkspaceError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            load(&mut runtime, "vohtori-workspace 1\nx a2 n1\n".as_bytes()),
+            Err(WorkspaceError::Malformed(2))
+        ));
+    }
+}
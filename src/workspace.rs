@@ -0,0 +1,226 @@
+//! Saving the variables of a session to disk and restoring them later.
+//!
+//! A workspace is a text file starting with a version header, followed by one
+//! variable per line as its name and its value in prefix notation:
+//!
+//! ```text
+//! vohtori-workspace 2
+//! xs a3 n1 n2 n3
+//! names m1 s6e616d65 a2 n1 n2
+//! big r1,1,1000000000
+//! ```
+//!
+//! `n` is a number, `s` a string as hex encoded UTF-8, `a` an array of the
+//! given length, `r` a range as its first element, step and length, `m` a map
+//! of the given number of key-value pairs and `b` a builtin function. Version
+//! 1 had no ranges, so its files are still read. Lambdas capture their environment and native functions
+//! belong to the host, so neither is saved.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Write as _},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use vohtori::{Array, Function, Map, Number, Runtime, Value};
+
+const HEADER: &str = "vohtori-workspace";
+const VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum WorkspaceError {
+    Io(io::Error),
+    MissingHeader,
+    UnsupportedVersion(String),
+    Malformed(usize),
+}
+
+impl Display for WorkspaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkspaceError::Io(err) => write!(f, "{}", err),
+            WorkspaceError::MissingHeader => write!(f, "Not a workspace file"),
+            WorkspaceError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported workspace version {}", version)
+            }
+            WorkspaceError::Malformed(line) => write!(f, "Malformed value on line {}", line),
+        }
+    }
+}
+
+impl From<io::Error> for WorkspaceError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Writes every variable that has been evaluated, returning the names of
+/// those that hold other functions than builtins and were skipped.
+pub fn save(runtime: &Runtime, mut out: impl Write) -> io::Result<Vec<String>> {
+    let mut variables: Vec<_> = runtime
+        .variables()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect();
+    variables.sort_by_key(|&(name, _)| name);
+
+    writeln!(out, "{} {}", HEADER, VERSION)?;
+
+    let mut skipped = vec![];
+    for (name, value) in variables {
+        let mut encoded = String::new();
+
+        if encode(value, &mut encoded) {
+            writeln!(out, "{}{}", name, encoded)?;
+        } else {
+            skipped.push(name.to_owned());
+        }
+    }
+
+    Ok(skipped)
+}
+
+/// Whether files of the given version can be read. Every version is a
+/// subset of the next one.
+fn supported(version: &str) -> bool {
+    version
+        .parse()
+        .is_ok_and(|v: u32| (1..=VERSION).contains(&v))
+}
+
+/// Binds every variable of a workspace, returning how many there were.
+pub fn load(runtime: &mut Runtime, input: impl BufRead) -> Result<usize, WorkspaceError> {
+    let mut lines = input.lines();
+
+    let header = lines
+        .next()
+        .transpose()?
+        .ok_or(WorkspaceError::MissingHeader)?;
+    match header.split_once(' ') {
+        Some((HEADER, version)) if supported(version) => (),
+        Some((HEADER, version)) => {
+            return Err(WorkspaceError::UnsupportedVersion(version.to_owned()))
+        }
+        _ => return Err(WorkspaceError::MissingHeader),
+    }
+
+    let mut variables = vec![];
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        // The header is line 1.
+        let malformed = || WorkspaceError::Malformed(i + 2);
+
+        let mut tokens = line.split_whitespace();
+        let name = tokens.next().ok_or_else(malformed)?;
+        let value = decode(&mut tokens).ok_or_else(malformed)?;
+
+        if tokens.next().is_some() {
+            return Err(malformed());
+        }
+
+        variables.push((name.to_owned(), value));
+    }
+
+    // Nothing is bound unless the whole file could be read.
+    let count = variables.len();
+    for (name, value) in variables {
+        runtime.push_var(&name, value);
+    }
+
+    Ok(count)
+}
+
+pub fn save_file(runtime: &Runtime, path: &Path) -> io::Result<Vec<String>> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let skipped = save(runtime, &mut out)?;
+    out.flush()?;
+
+    Ok(skipped)
+}
+
+pub fn load_file(runtime: &mut Runtime, path: &Path) -> Result<usize, WorkspaceError> {
+    load(runtime, BufReader::new(File::open(path)?))
+}
+
+/// Appends the tokens of `value`, each preceded by a space. Returns `false`
+/// if it contains a function that is not a builtin.
+fn encode(value: &Value, out: &mut String) -> bool {
+    match value {
+        Value::Number(number) => write!(out, " n{}", number.value).unwrap(),
+        Value::String(string) => {
+            out.push_str(" s");
+            for byte in string.bytes() {
+                write!(out, "{:02x}", byte).unwrap();
+            }
+        }
+        Value::Array(arr) if arr.as_progression().is_some() => {
+            let (start, step) = arr.as_progression().unwrap();
+            write!(out, " r{},{},{}", start, step, arr.len()).unwrap();
+        }
+        Value::Array(arr) => {
+            write!(out, " a{}", arr.len()).unwrap();
+            return arr.iter().all(|v| encode(&v, out));
+        }
+        Value::Map(map) => {
+            write!(out, " m{}", map.len()).unwrap();
+            return map
+                .entries()
+                .iter()
+                .all(|(k, v)| encode(k, out) && encode(v, out));
+        }
+        Value::Function(Function::Builtin(name)) => write!(out, " b{}", name).unwrap(),
+        Value::Function(_) => return false,
+    }
+
+    true
+}
+
+fn decode<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Value> {
+    let mut token = tokens.next()?.chars();
+    let tag = token.next()?;
+    let rest = token.as_str();
+
+    Some(match tag {
+        'n' => Value::Number(Number {
+            value: rest.parse().ok()?,
+        }),
+        's' => {
+            let bytes = (0..rest.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(rest.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+
+            Value::String(String::from_utf8(bytes).ok()?)
+        }
+        'a' => {
+            let len: usize = rest.parse().ok()?;
+
+            Value::Array((0..len).map(|_| decode(tokens)).collect::<Option<_>>()?)
+        }
+        'r' => {
+            let mut fields = rest.split(',');
+            let start = fields.next()?.parse().ok()?;
+            let step = fields.next()?.parse().ok()?;
+            let len = fields.next()?.parse().ok()?;
+
+            if fields.next().is_some() {
+                return None;
+            }
+
+            Value::Array(Array::progression(start, step, len))
+        }
+        'm' => {
+            let len: usize = rest.parse().ok()?;
+            let mut map = BTreeMap::new();
+
+            for _ in 0..len {
+                map.insert(decode(tokens)?, decode(tokens)?);
+            }
+
+            Value::Map(Map::new(map))
+        }
+        'b' => Value::Function(Function::Builtin(vohtori::builtin(rest)?.name.to_owned())),
+        _ => return None,
+    })
+}
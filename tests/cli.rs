@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Runs the interpreter with `args` and `stdin` piped in, returning what it
+/// printed.
+fn run(args: &[&str], stdin: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vohtori"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn eval_prints_the_result() {
+    assert_eq!(run(&["-e", "1 + 2"], ""), "3\n");
+    assert_eq!(run(&["--eval", "xs = 1..3\n+ xs"], ""), "[1 2 3]\n6\n");
+}
+
+#[test]
+fn repeated_evals_share_variables() {
+    assert_eq!(run(&["-e", "x = 2", "-e", "x * 3"], ""), "2\n6\n");
+}
+
+#[test]
+fn eval_reads_piped_stdin() {
+    assert_eq!(run(&["-e", "stdin"], "1 2\n3 4\n"), "[[1 2] [3 4]]\n");
+}
+
+#[test]
+fn stdin_is_only_read_when_used() {
+    // Stdin is left open, so reading it would block until the deadline.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vohtori"))
+        .args(["-e", "1 + 2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("waited for stdin although the script does not use it");
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    assert!(status.success());
+}
+
+#[test]
+fn piped_sessions_leave_the_history_alone() {
+    let home = std::env::temp_dir().join(format!("vohtori-home-{}", std::process::id()));
+    fs::create_dir_all(&home).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vohtori"))
+        .arg("-i")
+        .env("HOME", &home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(b"1 + 2\n")?;
+            child.wait_with_output()
+        })
+        .unwrap();
+    let written = home.join(".vohtori_history").exists();
+    fs::remove_dir_all(&home).unwrap();
+
+    assert!(output.status.success());
+    assert!(!written);
+}
+
+#[test]
+fn input_file_replaces_stdin() {
+    let path = std::env::temp_dir().join(format!("vohtori-input-{}", std::process::id()));
+    fs::write(&path, "1 2\n3 4\n").unwrap();
+
+    let output = run(&["--input", path.to_str().unwrap(), "-e", "stdin"], "5\n");
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output, "[[1 2] [3 4]]\n");
+}